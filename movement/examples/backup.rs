@@ -0,0 +1,80 @@
+// Saves the EEPROM items of a protocol 1 servo to a file, restores them, or
+// compares two saved files. `reset` saves the items, resets the servo to its
+// factory settings, then restores them.
+// Usage: backup save <port> <baud rate> <id> <file>
+//        backup restore <port> <baud rate> <id> <file>
+//        backup reset <port> <baud rate> <id> <file>
+//        backup diff <file> <file>
+use connection::usb;
+use movement::dynamixel::backup::Backup;
+use movement::dynamixel::{Dynamixel, DynamixelID};
+use serialport::SerialPort;
+use std::error::Error;
+
+const USAGE: &str = "Usage: backup save <port> <baud rate> <id> <file>
+       backup restore <port> <baud rate> <id> <file>
+       backup reset <port> <baud rate> <id> <file>
+       backup diff <file> <file>";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match (args.first().map(String::as_str), args.len()) {
+        (Some("save"), 5) => {
+            let backup = connect(&args[1..])?.backup()?;
+            backup.save(&args[4])?;
+            println!(
+                "Saved {} items of {} to {}",
+                backup.items.len(),
+                backup.model_name,
+                args[4]
+            );
+        }
+        (Some("restore"), 5) => {
+            let backup = Backup::from_file(&args[4])?;
+            let written = connect(&args[1..])?.restore(&backup)?;
+            println!("Restored {} items", written.len());
+            for name in written {
+                println!("    {}", name);
+            }
+        }
+        (Some("reset"), 5) => {
+            let mut dxl = connect(&args[1..])?;
+            let backup = dxl.backup()?;
+            backup.save(&args[4])?;
+            dxl.factory_reset()?;
+            println!(
+                "Reset {} and restored {} items saved to {}",
+                backup.model_name,
+                backup.items.len(),
+                args[4]
+            );
+        }
+        (Some("diff"), 3) => {
+            let before = Backup::from_file(&args[1])?;
+            let after = Backup::from_file(&args[2])?;
+            if before.model_number != after.model_number {
+                println!(
+                    "Models differ: {} -> {}",
+                    before.model_name, after.model_name
+                );
+            }
+            for difference in before.diff(&after) {
+                println!("{}", difference);
+            }
+        }
+        _ => return Err(USAGE.into()),
+    }
+
+    Ok(())
+}
+
+fn connect(args: &[String]) -> Result<Dynamixel<Box<dyn SerialPort>>, Box<dyn Error>> {
+    let baud_rate = args[1].parse()?;
+    let id = args[2].parse()?;
+
+    let port = usb::connect_usb(&args[0], baud_rate);
+    let mut dxl = Dynamixel::new_empty(port, DynamixelID::ID(id));
+    dxl.baud_rate = Some(baud_rate);
+
+    Ok(dxl)
+}
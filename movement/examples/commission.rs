@@ -0,0 +1,91 @@
+// Changes the ID & baud rate of protocol 1 servos. `set` changes a single
+// servo, while `batch` gives each ID in the list to a new servo in turn,
+// waiting for the operator to connect each one, searching every baud rate.
+// Usage: commission set <port> <baud rate> <id> <new id> [new baud rate]
+//        commission batch <port> <id,id,...> [new baud rate]
+use connection::usb;
+use movement::dynamixel::commission::assign_batch;
+use movement::dynamixel::scan::ScanOptions;
+use movement::dynamixel::{Dynamixel, DynamixelID, Protocol};
+use std::error::Error;
+use std::io::BufRead;
+
+const USAGE: &str = "Usage: commission set <port> <baud rate> <id> <new id> [new baud rate]
+       commission batch <port> <id,id,...> [new baud rate]";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("set") if args.len() >= 5 => set(&args[1..]),
+        Some("batch") if args.len() >= 3 => batch(&args[1..]),
+        _ => Err(USAGE.into()),
+    }
+}
+
+fn set(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let baud_rate = args[1].parse()?;
+    let id = args[2].parse()?;
+    let new_id = args[3].parse()?;
+
+    let port = usb::connect_usb(&args[0], baud_rate);
+    let mut dxl = Dynamixel::new_empty(port, DynamixelID::ID(id));
+    dxl.baud_rate = Some(baud_rate);
+
+    dxl.change_id(new_id)?;
+    println!("Servo {} is now servo {}", id, new_id);
+    if let Some(new_baud_rate) = args.get(4) {
+        dxl.change_baud_rate(new_baud_rate.parse()?)?;
+        println!(
+            "Servo {} now communicates at {}bps",
+            new_id,
+            dxl.baud_rate.unwrap()
+        );
+    }
+
+    Ok(())
+}
+
+fn batch(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let ids = args[1]
+        .split(',')
+        .map(str::parse)
+        .collect::<Result<Vec<u8>, _>>()?;
+    let baud_rate = match args.get(2) {
+        Some(baud_rate) => Some(baud_rate.parse()?),
+        None => None,
+    };
+    let options = ScanOptions {
+        protocols: vec![Protocol::One],
+        ..ScanOptions::default()
+    };
+
+    let mut port = usb::connect_usb(&args[0], 1_000_000);
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    let assigned = assign_batch(&mut port, &ids, &options, baud_rate, |_, id, error| {
+        if let Some(error) = error {
+            println!("Could not commission servo {}: {}", id, error);
+        }
+        println!(
+            "Connect the servo to become servo {}, then press enter (q to stop)",
+            id
+        );
+        match lines.next() {
+            Some(Ok(line)) => line.trim() != "q",
+            _ => false,
+        }
+    })?;
+
+    for assignment in assigned.iter() {
+        println!(
+            "Servo {} at {}bps is now servo {} at {}bps",
+            assignment.found.id, assignment.found.baud_rate, assignment.id, assignment.baud_rate
+        );
+    }
+    if assigned.len() < ids.len() {
+        println!("Stopped after {} of {} servos", assigned.len(), ids.len());
+    }
+
+    Ok(())
+}
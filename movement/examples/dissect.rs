@@ -0,0 +1,49 @@
+// Prints the packets of a capture file, or of a raw log of the bytes on the
+// bus, annotated with the items of the given model's control table.
+// Usage: dissect <file> [model number]
+use movement::dynamixel::capture::{Capture, Direction};
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::dissector::Dissector;
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("Usage: dissect <file> [model number]")?;
+    let model = match args.next() {
+        Some(number) => Some(builtin_model(number.parse()?).ok_or("Unknown model number")?),
+        None => None,
+    };
+
+    let mut dissector = Dissector::new(model);
+    let bytes = std::fs::read(&path)?;
+    let capture = std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|source| Capture::from_toml(source).ok());
+
+    match capture {
+        Some(capture) => {
+            for packet in capture.packets {
+                let direction = match packet.direction {
+                    Direction::Sent => "->",
+                    Direction::Received => "<-",
+                };
+
+                for segment in dissector.dissect_captured(&packet) {
+                    println!(
+                        "{:>10.3}ms {} {}",
+                        packet.time as f64 / 1000.0,
+                        direction,
+                        segment
+                    );
+                }
+            }
+        }
+        None => {
+            for segment in dissector.dissect(&bytes) {
+                println!("{}", segment);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+// Scans a serial port for any connected Dynamixels, at every ID, standard
+// baud rate & protocol. Usage: scan_bus [port]
+use connection::usb;
+use movement::dynamixel::scan::{scan, ScanOptions};
+use movement::dynamixel::DynamixelError;
+use std::io::Write;
+
+fn main() -> Result<(), DynamixelError> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "/dev/ttyACM0".to_string());
+    let mut port = usb::connect_usb(&path, 1_000_000);
+
+    let found = scan(&mut port, &ScanOptions::default(), |progress| {
+        print!(
+            "\rProtocol {:?} at {}bps: ID {:>3} ({}/{}, {} found)",
+            progress.protocol,
+            progress.baud_rate,
+            progress.id,
+            progress.completed,
+            progress.total,
+            progress.found
+        );
+        let _ = std::io::stdout().flush();
+    })?;
+    println!();
+
+    for servo in found {
+        println!(
+            "ID {} at {}bps using protocol {:?}: model {:?}, firmware {:?}",
+            servo.id, servo.baud_rate, servo.protocol, servo.model_number, servo.firmware_version
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+// Listens to a bus driven by another controller without transmitting,
+// printing each instruction alongside its response until the port is closed.
+// Packets are also saved to a capture file if one is given.
+// Usage: sniff <port> <baud rate> [model number] [capture file]
+use connection::usb;
+use movement::dynamixel::capture::CaptureWriter;
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::dissector::Dissector;
+use movement::dynamixel::sniffer::Sniffer;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let usage = "Usage: sniff <port> <baud rate> [model number] [capture file]";
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or(usage)?;
+    let baud_rate = args.next().ok_or(usage)?.parse()?;
+    let model = match args.next() {
+        Some(number) => Some(builtin_model(number.parse()?).ok_or("Unknown model number")?),
+        None => None,
+    };
+    let mut capture = match args.next() {
+        Some(path) => Some(CaptureWriter::new(File::create(path)?)?),
+        None => None,
+    };
+
+    let port = usb::connect_usb(&path, baud_rate);
+    let mut sniffer = Sniffer::new(port, Dissector::new(model));
+    if capture.is_some() {
+        sniffer.start_capture();
+    }
+
+    let result = loop {
+        match sniffer.poll() {
+            Ok(sniffed) => {
+                for sniffed in sniffed {
+                    println!("{}", sniffed);
+                }
+                save(&mut sniffer, capture.as_mut())?;
+            }
+            Err(err) => break err,
+        }
+    };
+
+    // Report the last instruction, which may still be awaiting a response
+    for sniffed in sniffer.finish() {
+        println!("{}", sniffed);
+    }
+    save(&mut sniffer, capture.as_mut())?;
+
+    Err(result.into())
+}
+
+/// Saves each packet decoded by the sniffer, at the time it was read
+fn save<R: Read>(
+    sniffer: &mut Sniffer<R>,
+    capture: Option<&mut CaptureWriter<File>>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(capture) = capture {
+        for packet in sniffer.take_captured() {
+            capture.record_at(
+                Duration::from_micros(packet.time),
+                packet.direction,
+                packet.bytes,
+            )?;
+        }
+    }
+
+    Ok(())
+}
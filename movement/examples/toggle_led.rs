@@ -3,18 +3,13 @@
 
 // TODO: Update this to work with newer APIs & broader range of hardware
 use connection::usb;
-use movement::dynamixel::{protocol_one::ProtocolOne, Dynamixel};
+use movement::dynamixel::{protocol_one::ProtocolOne, Dynamixel, DynamixelError, DynamixelID};
 
-fn main() {
-    // Enable the LED on a connected Dynamixel (ID: 1, Model: AX-12A)
+fn main() -> Result<(), DynamixelError> {
+    // Toggle the LED on a connected protocol 1 Dynamixel (ID: 1)
     let mut port = usb::connect_usb("/dev/ttyACM0", 1_000_000);
-    let mut dxl = Dynamixel::new_empty(&mut port);
+    let mut dxl = Dynamixel::new_empty(&mut port, DynamixelID::ID(1));
+    dxl.detect_model()?;
 
-    let led_state = match dxl.read(25, 1).parameters[0] {
-        0 => 1,
-        1 => 0,
-        _ => panic!("Invalid LED state!"),
-    };
-
-    dxl.write(25, led_state);
+    dxl.led().toggle()
 }
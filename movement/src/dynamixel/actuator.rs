@@ -0,0 +1,275 @@
+//! # Generic actuator APIs
+//! This file implements the generic [`Servo`] and [`Motor`] traits for
+//! protocol 1 Dynamixels, so that they can be driven in the same way as any
+//! other actuator. Positions & speeds are given in the raw units of the Goal
+//! Position & Moving Speed items, and the model of the servo must be known
+//! (see `detect_model`) before either trait is used.
+//!
+//! A Dynamixel acts as a servo in joint mode, and as a motor in wheel mode,
+//! where bit 10 of the speed sets the direction of rotation. The servo is in
+//! wheel mode when both of its angle limits are 0, so the limits used in
+//! joint mode are remembered when switching to wheel mode, and restored when
+//! switching back. Positions cannot be set in wheel mode, nor can a direction
+//! be given with the speed in joint mode.
+//!
+//! Positions & speeds may instead be given as physical quantities, which are
+//! converted to & from raw values using the resolution of the servo's model
+//! (see [`units`](super::units)), using the protocol of the servo's model.
+//!
+//! Protocol 2 servos, such as the X series, have no Moving Speed item and
+//! select their mode with the Operating Mode item instead of the angle
+//! limits, so the modes, positions & speeds of protocol 2 servos are
+//! rejected with `DynamixelError::UnsupportedProtocol` rather than sending
+//! them protocol 1 instructions.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID, DynamixelMode};
+//! use movement::{Motor, Servo};
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!     dxl.detect_model().unwrap();
+//!     dxl.write_item("CCW Angle Limit", 800).unwrap();
+//!     assert_eq!(dxl.get_mode().unwrap(), DynamixelMode::Joint);
+//!
+//!     // Turn clockwise at speed 100
+//!     dxl.set_mode(DynamixelMode::Wheel).unwrap();
+//!     dxl.set_speed(1024 + 100).unwrap();
+//!     assert_eq!(dxl.get_speed(), 1124);
+//!     assert_eq!(dxl.read_item("CCW Angle Limit").unwrap(), 0);
+//!     assert!(dxl.set_pos(512).is_err());
+//!
+//!     // The previous joint limits are restored
+//!     dxl.set_mode(DynamixelMode::Joint).unwrap();
+//!     assert_eq!(dxl.read_item("CCW Angle Limit").unwrap(), 800);
+//!     dxl.set_pos(768).unwrap();
+//!     assert_eq!(dxl.read_item("Goal Position").unwrap(), 768);
+//!     assert!(dxl.set_speed(1124).is_err());
+//! }
+//! ```
+
+use super::control_table::ControlTableItem;
+use super::protocol_one::ProtocolOne;
+use super::units::Resolution;
+use super::{Dynamixel, DynamixelError, DynamixelMode};
+use crate::{Motor, Servo};
+use sensor::DataValue;
+use std::io::{Read, Write};
+
+/// The magnitude of the Moving Speed item at full speed
+const MAX_SPEED: usize = 1023;
+
+/// The bit of the Moving Speed item which sets the direction in wheel mode
+const DIRECTION_BIT: usize = 1024;
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Reads the mode of the servo from its angle limits. The limits are
+    /// remembered if the servo is in joint mode.
+    pub fn get_mode(&mut self) -> Result<DynamixelMode, DynamixelError> {
+        self.require_protocol_one("read the mode")?;
+        let cw_limit = self.read_item("CW Angle Limit")?;
+        let ccw_limit = self.read_item("CCW Angle Limit")?;
+
+        let mode = if cw_limit == 0 && ccw_limit == 0 {
+            DynamixelMode::Wheel
+        } else {
+            self.joint_limits = Some((cw_limit, ccw_limit));
+            DynamixelMode::Joint
+        };
+        self.mode = Some(mode);
+
+        Ok(mode)
+    }
+
+    /// Switches the servo between joint & wheel mode. Wheel mode is set by
+    /// setting both angle limits to 0, while joint mode restores the limits
+    /// the servo had before it entered wheel mode. If those limits are not
+    /// known, the servo is allowed to use its full range of positions.
+    pub fn set_mode(&mut self, mode: DynamixelMode) -> Result<(), DynamixelError> {
+        self.require_protocol_one("set the mode")?;
+
+        // The joint limits must be read before they are overwritten
+        if self.current_mode()? == mode {
+            return Ok(());
+        }
+
+        let (cw_limit, ccw_limit) = match mode {
+            DynamixelMode::Wheel => (0, 0),
+            DynamixelMode::Joint => match self.joint_limits {
+                Some(limits) => limits,
+                None => match self.get_item("CCW Angle Limit")?.range {
+                    Some((_, max)) => (0, max as u64),
+                    None => (0, 0),
+                },
+            },
+        };
+
+        self.write_item("CW Angle Limit", cw_limit)?;
+        self.write_item("CCW Angle Limit", ccw_limit)?;
+        self.mode = Some(mode);
+
+        Ok(())
+    }
+
+    /// Sets the goal position as an angle in degrees or radians
+    pub fn set_angle(&mut self, angle: &DataValue<isize>) -> Result<(), DynamixelError> {
+        let (resolution, item) = self.physical_item("Goal Position")?;
+        let pos = resolution.from_physical(&item, angle)?;
+
+        self.set_pos(pos as usize)
+    }
+
+    /// Reads the present position as an angle in degrees
+    pub fn get_angle(&mut self) -> Result<DataValue<isize>, DynamixelError> {
+        self.read_physical("Present Position")
+    }
+
+    /// Sets the moving speed in RPM, where a negative speed turns clockwise
+    /// in wheel mode
+    ///
+    /// ```
+    /// use movement::dynamixel::control_table::builtin_model;
+    /// use movement::dynamixel::protocol_one::ProtocolOne;
+    /// use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+    /// use movement::dynamixel::{Dynamixel, DynamixelID, DynamixelMode};
+    /// use sensor::{DataUnit, DataValue};
+    ///
+    /// fn main() {
+    ///     let mut bus = SimulatedBus::new();
+    ///     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+    ///     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+    ///     dxl.detect_model().unwrap();
+    ///     dxl.set_mode(DynamixelMode::Wheel).unwrap();
+    ///
+    ///     let rpm = DataValue::from_f64(DataUnit::RevolutionsPerMinute, -3, -55.5);
+    ///     dxl.set_rpm(&rpm).unwrap();
+    ///     assert_eq!(dxl.read_item("Moving Speed").unwrap(), 1024 + 500);
+    /// }
+    /// ```
+    pub fn set_rpm(&mut self, speed: &DataValue<isize>) -> Result<(), DynamixelError> {
+        let (resolution, item) = self.physical_item("Moving Speed")?;
+        let raw = resolution.from_physical(&item, speed)?;
+
+        self.set_speed(raw as usize)
+    }
+
+    /// Reads the present speed in RPM, which is negative when turning
+    /// clockwise
+    pub fn get_rpm(&mut self) -> Result<DataValue<isize>, DynamixelError> {
+        self.read_physical("Present Speed")
+    }
+
+    /// Reads an item as a physical quantity, such as the Present Load in
+    /// percent or the Present Voltage in volts
+    pub fn read_physical(&mut self, name: &str) -> Result<DataValue<isize>, DynamixelError> {
+        let (resolution, item) = self.physical_item(name)?;
+        let raw = self.read_model_item(name)?;
+
+        resolution
+            .to_physical(&item, raw)
+            .ok_or_else(|| DynamixelError::UnitMismatch {
+                name: name.to_string(),
+                expected: None,
+                found: sensor::DataUnit::Other,
+            })
+    }
+
+    /// Writes a physical quantity to an item
+    pub fn write_physical(
+        &mut self,
+        name: &str,
+        value: &DataValue<isize>,
+    ) -> Result<(), DynamixelError> {
+        let (resolution, item) = self.physical_item(name)?;
+        let raw = resolution.from_physical(&item, value)?;
+
+        self.write_model_item(name, raw)
+    }
+
+    /// The resolution of the servo's model, along with the named item
+    fn physical_item(&self, name: &str) -> Result<(Resolution, ControlTableItem), DynamixelError> {
+        let model = self
+            .model
+            .as_ref()
+            .ok_or_else(|| DynamixelError::UnknownItem(name.to_string()))?;
+        let item = model
+            .item(name)
+            .ok_or_else(|| DynamixelError::UnknownItem(name.to_string()))?;
+        let resolution =
+            Resolution::of(model.number).ok_or(DynamixelError::UnknownModel(model.number))?;
+
+        Ok((resolution, item.clone()))
+    }
+
+    /// The mode of the servo, which is only read if it is not yet known
+    fn current_mode(&mut self) -> Result<DynamixelMode, DynamixelError> {
+        match self.mode {
+            Some(mode) => Ok(mode),
+            None => self.get_mode(),
+        }
+    }
+}
+
+impl<C> Servo for Dynamixel<C>
+where
+    C: Read + Write,
+{
+    type Error = DynamixelError;
+
+    /// Sets the goal position of the servo, which must be in joint mode
+    fn set_pos(&mut self, pos: usize) -> Result<(), DynamixelError> {
+        self.require_protocol_one("set a goal position")?;
+        let mode = self.current_mode()?;
+        if mode == DynamixelMode::Wheel {
+            return Err(DynamixelError::WrongMode {
+                mode,
+                operation: "set a goal position",
+            });
+        }
+
+        self.write_item("Goal Position", pos as u64)
+    }
+}
+
+impl<C> Motor for Dynamixel<C>
+where
+    C: Read + Write,
+{
+    type Error = DynamixelError;
+
+    /// Sets the moving speed of the servo, which is the speed it turns at in
+    /// wheel mode, or the speed it moves to its goal position in joint mode.
+    /// The direction bit may only be set in wheel mode.
+    fn set_speed(&mut self, speed: usize) -> Result<(), DynamixelError> {
+        self.require_protocol_one("set a speed")?;
+        let mode = self.current_mode()?;
+        if mode == DynamixelMode::Joint && speed & DIRECTION_BIT != 0 {
+            return Err(DynamixelError::WrongMode {
+                mode,
+                operation: "set a direction of rotation",
+            });
+        }
+
+        self.write_item("Moving Speed", speed as u64)?;
+        self.speed = speed;
+
+        Ok(())
+    }
+
+    /// Gets the last speed set on the servo
+    fn get_speed(&self) -> usize {
+        self.speed
+    }
+
+    fn get_max_speed(&self) -> usize {
+        MAX_SPEED
+    }
+}
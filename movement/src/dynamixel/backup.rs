@@ -0,0 +1,384 @@
+//! # Control table backups
+//! The limits, compliance & communication settings of a servo are kept in the
+//! EEPROM area of its control table, and are lost when the servo is reset to
+//! its factory settings or swapped for another. A [`Backup`] holds the value
+//! of every EEPROM item of a protocol 1 servo along with its model, and can
+//! be saved as TOML, restored to a servo of the same model, or compared with
+//! another backup.
+//!
+//! ```
+//! use movement::dynamixel::backup::Backup;
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID};
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!     dxl.detect_model().unwrap();
+//!
+//!     dxl.write_item("CW Angle Limit", 200).unwrap();
+//!     let tuned = Backup::from_toml(&dxl.backup().unwrap().to_toml().unwrap()).unwrap();
+//!     assert_eq!(tuned.model_number, 12);
+//!     assert_eq!(tuned.value("CW Angle Limit"), Some(200));
+//!
+//!     dxl.write_item("CW Angle Limit", 0).unwrap();
+//!     let differences = tuned.diff(&dxl.backup().unwrap());
+//!     assert_eq!(differences.len(), 1);
+//!     assert_eq!(differences[0].to_string(), "CW Angle Limit: 200 -> 0");
+//!
+//!     // Only the items which differ are written
+//!     assert_eq!(dxl.restore(&tuned).unwrap(), vec!["CW Angle Limit"]);
+//!     assert_eq!(dxl.read_item("CW Angle Limit").unwrap(), 200);
+//! }
+//! ```
+
+use super::control_table::Model;
+use super::protocol_one::{self, PacketDecoder, ProtocolOne};
+use super::servo_connection::BaudRate;
+use super::{
+    AccessLevel, DataBytes, Dynamixel, DynamixelError, DynamixelID, RangePolicy, StatusReturnLevel,
+};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// The ways in which a backup can fail to be saved or loaded
+#[derive(Debug)]
+pub enum BackupError {
+    /// The file could not be read or written
+    Io(std::io::Error),
+    /// The backup is not valid TOML, or is missing required fields
+    Parse(String),
+}
+
+impl std::fmt::Display for BackupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BackupError::Io(err) => write!(f, "unable to access backup: {}", err),
+            BackupError::Parse(err) => write!(f, "unable to parse backup: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+/// The value of a single item when it was backed up
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupItem {
+    pub name: String,
+    pub value: i64,
+}
+
+/// The EEPROM items of a servo, in the order of their addresses
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backup {
+    pub model_number: u16,
+    pub model_name: String,
+    pub items: Vec<BackupItem>,
+}
+
+/// An item whose value differs between two backups, or which is only in one
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub name: String,
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+}
+
+impl std::fmt::Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let value = |value: Option<i64>| match value {
+            Some(value) => value.to_string(),
+            None => "missing".to_string(),
+        };
+
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name,
+            value(self.before),
+            value(self.after)
+        )
+    }
+}
+
+impl Backup {
+    /// Finds the value of an item by name
+    pub fn value(&self, name: &str) -> Option<i64> {
+        self.items
+            .iter()
+            .find(|item| item.name == name)
+            .map(|item| item.value)
+    }
+
+    /// Lists the items which differ from another backup, in the order of
+    /// this backup followed by any items only in the other
+    pub fn diff(&self, other: &Backup) -> Vec<Difference> {
+        let mut differences: Vec<Difference> = self
+            .items
+            .iter()
+            .map(|item| Difference {
+                name: item.name.clone(),
+                before: Some(item.value),
+                after: other.value(&item.name),
+            })
+            .filter(|difference| difference.before != difference.after)
+            .collect();
+
+        differences.extend(
+            other
+                .items
+                .iter()
+                .filter(|item| self.value(&item.name).is_none())
+                .map(|item| Difference {
+                    name: item.name.clone(),
+                    before: None,
+                    after: Some(item.value),
+                }),
+        );
+
+        differences
+    }
+
+    /// Parses a backup saved as TOML
+    pub fn from_toml(source: &str) -> Result<Backup, BackupError> {
+        toml::from_str(source).map_err(|err| BackupError::Parse(err.to_string()))
+    }
+
+    /// Reads & parses a backup from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Backup, BackupError> {
+        let source = std::fs::read_to_string(path).map_err(BackupError::Io)?;
+        Backup::from_toml(&source)
+    }
+
+    /// Serialises the backup as TOML
+    pub fn to_toml(&self) -> Result<String, BackupError> {
+        toml::to_string(self).map_err(|err| BackupError::Parse(err.to_string()))
+    }
+
+    /// Saves the backup to a TOML file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BackupError> {
+        std::fs::write(path, self.to_toml()?).map_err(BackupError::Io)
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Reads every EEPROM item of the servo in a single read, detecting the
+    /// model of the servo first if it is not known
+    pub fn backup(&mut self) -> Result<Backup, DynamixelError> {
+        let model = self.loaded_model()?;
+        let items = model.eeprom_items();
+        let end = items
+            .iter()
+            .map(|item| item.data.address + item.data.size as u16)
+            .max()
+            .unwrap_or(0);
+        let status = ProtocolOne::read(self, 0, end as u64)?;
+
+        let items = items
+            .into_iter()
+            .map(|item| {
+                let start = item.data.address as usize;
+                let bytes = status
+                    .parameters
+                    .get(start..start + item.data.size as usize)
+                    .ok_or_else(|| {
+                        DynamixelError::InvalidPacket(format!(
+                            "Read {} bytes, but item {} ends at {}",
+                            status.parameters.len(),
+                            item.name,
+                            end
+                        ))
+                    })?;
+                let signed =
+                    item.data.signed || matches!(item.data.range, Some((min, _)) if min < 0);
+                let value = DataBytes::decode(bytes, signed).ok_or_else(|| {
+                    DynamixelError::InvalidPacket(format!(
+                        "Item {} has an invalid size of {} bytes!",
+                        item.name, item.data.size
+                    ))
+                })?;
+
+                Ok(BackupItem {
+                    name: item.name.clone(),
+                    value: value.into(),
+                })
+            })
+            .collect::<Result<Vec<BackupItem>, DynamixelError>>()?;
+
+        Ok(Backup {
+            model_number: model.number,
+            model_name: model.name.clone(),
+            items,
+        })
+    }
+
+    /// The control table of the servo, detecting the model if it is not known
+    fn loaded_model(&mut self) -> Result<Model, DynamixelError> {
+        if self.model.is_none() {
+            ProtocolOne::detect_model(self)?;
+        }
+
+        self.model
+            .clone()
+            .ok_or_else(|| DynamixelError::UnknownItem("Model Number".to_string()))
+    }
+
+    /// Checks every writable item of a backup against the control table,
+    /// rejecting values out of range regardless of the `range_policy`
+    fn check_backup(&mut self, backup: &Backup) -> Result<Vec<(String, u64)>, DynamixelError> {
+        let policy = std::mem::replace(&mut self.range_policy, RangePolicy::Error);
+        let checked = backup
+            .items
+            .iter()
+            .filter_map(|item| match self.get_item(&item.name) {
+                Ok(data) if data.access == AccessLevel::Read => None,
+                Ok(_) => Some(
+                    self.check_write(&item.name, item.value as u64)
+                        .map(|value| (item.name.clone(), value)),
+                ),
+                Err(err) => Some(Err(err)),
+            })
+            .collect();
+        self.range_policy = policy;
+
+        checked
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write + BaudRate,
+{
+    /// Writes the items of a backup to the servo, returning the names of the
+    /// items which were written. Read only items, and items which already
+    /// hold the backed up value, are skipped.
+    ///
+    /// Every value is checked to be in range before anything is written, so
+    /// that an invalid backup is never partly restored. The baud rate & ID
+    /// are restored last with [`Dynamixel::change_baud_rate`] &
+    /// [`Dynamixel::change_id`], so are refused if another servo already
+    /// uses them.
+    pub fn restore(&mut self, backup: &Backup) -> Result<Vec<String>, DynamixelError> {
+        let model = self.loaded_model()?;
+        if model.number != backup.model_number {
+            return Err(DynamixelError::ModelMismatch {
+                expected: backup.model_number,
+                found: model.number,
+            });
+        }
+
+        let items = self.check_backup(backup)?;
+        let current = self.backup()?;
+        let mut written = vec![];
+
+        for (name, value) in items.iter() {
+            let unchanged = current.value(name) == backup.value(name);
+            if unchanged || name == "ID" || name == "Baud Rate" {
+                continue;
+            }
+
+            ProtocolOne::write_item(self, name, *value)?;
+            written.push(name.clone());
+        }
+
+        if let Some(value) = backup.value("Baud Rate") {
+            if current.value("Baud Rate") != Some(value) {
+                let bits_per_second =
+                    protocol_one::baud_rate(value as u8).ok_or(DynamixelError::OutOfRange {
+                        name: "Baud Rate".to_string(),
+                        value,
+                    })?;
+                self.change_baud_rate(bits_per_second)?;
+                written.push("Baud Rate".to_string());
+            }
+        }
+
+        if let Some(value) = backup.value("ID") {
+            if current.value("ID") != Some(value) {
+                self.change_id(value as u8)?;
+                written.push("ID".to_string());
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Backs up the servo, resets it to its factory settings, then restores
+    /// the backup, returning the backup. As a reset servo returns to ID 1 at
+    /// its model's default baud rate, the reset is refused if another servo
+    /// already responds there.
+    ///
+    /// ```
+    /// use movement::dynamixel::control_table::builtin_model;
+    /// use movement::dynamixel::protocol_one::ProtocolOne;
+    /// use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+    /// use movement::dynamixel::{Dynamixel, DynamixelID};
+    ///
+    /// fn main() {
+    ///     let mut bus = SimulatedBus::new();
+    ///     bus.add_servo(SimulatedDynamixel::new(3, builtin_model(12).unwrap()));
+    ///     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(3));
+    ///     dxl.detect_model().unwrap();
+    ///     dxl.write_item("Max Torque", 800).unwrap();
+    ///
+    ///     let backup = dxl.factory_reset().unwrap();
+    ///     assert_eq!(backup.value("Max Torque"), Some(800));
+    ///     assert_eq!(dxl.id, DynamixelID::ID(3));
+    ///     assert_eq!(dxl.connection_handler.servo(3).unwrap().get_item("Max Torque"), Some(800));
+    /// }
+    /// ```
+    pub fn factory_reset(&mut self) -> Result<Backup, DynamixelError> {
+        let backup = self.backup()?;
+        let model = self.loaded_model()?;
+
+        let factory_rate = model
+            .item("Baud Rate")
+            .and_then(|item| item.data.initial_value.as_ref()?.parse().ok())
+            .and_then(protocol_one::baud_rate)
+            .ok_or_else(|| DynamixelError::UnknownItem("Baud Rate".to_string()))?;
+        let current_rate = match self.baud_rate {
+            Some(rate) => rate,
+            None => backup
+                .value("Baud Rate")
+                .and_then(|value| protocol_one::baud_rate(value as u8))
+                .unwrap_or(factory_rate),
+        };
+
+        // Look for another servo where this one will be after the reset
+        if self.id != DynamixelID::ID(1) || current_rate != factory_rate {
+            BaudRate::set_baud_rate(self.connection_handler.as_mut(), factory_rate)?;
+            let collides = self.responds_at(1);
+            BaudRate::set_baud_rate(self.connection_handler.as_mut(), current_rate)?;
+            if collides? {
+                return Err(DynamixelError::IDInUse(1));
+            }
+        }
+
+        // The response may come from ID 1 at the factory baud rate
+        match ProtocolOne::reset(self) {
+            Ok(_)
+            | Err(DynamixelError::Timeout)
+            | Err(DynamixelError::Packet(_))
+            | Err(DynamixelError::IDMismatch { .. }) => {}
+            Err(err) => return Err(err),
+        }
+        self.decoder = PacketDecoder::new();
+
+        BaudRate::set_baud_rate(self.connection_handler.as_mut(), factory_rate)?;
+        self.id = DynamixelID::ID(1);
+        self.baud_rate = Some(factory_rate);
+        self.status_return_level = StatusReturnLevel::All;
+        self.verify()?;
+        ProtocolOne::read_information(self)?;
+
+        self.restore(&backup)?;
+        Ok(backup)
+    }
+}
@@ -0,0 +1,504 @@
+//! # Shared Dynamixel bus
+//! Dynamixels are daisy chained, so every servo in a robot is usually reached
+//! through a single serial port. A `DynamixelBus` owns that port and hands out
+//! a `BusServo` for each ID. Each servo keeps its own state (such as its
+//! control table), while every transaction on the bus is serialised, so that
+//! an instruction and its status packet are never interleaved with those of
+//! another servo.
+//!
+//! The bus can be cloned cheaply, allowing servos to be controlled from
+//! several threads at once. Instructions addressing several servos at once,
+//! such as sync write & bulk read, are sent through the bus itself.
+//!
+//! Status packets are waited for according to the baud rate & retry policy
+//! of the bus (see [`reliability`](super::reliability)), which every servo
+//! handle starts with.
+//!
+//! Every packet on the bus, whether exchanged through a servo handle or sent
+//! by the bus itself, can be recorded in a single [`Capture`] between
+//! `start_capture` & `stop_capture` (see [`capture`](super::capture)).
+//!
+//! ```
+//! use movement::dynamixel::bus::DynamixelBus;
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use std::thread;
+//!
+//! fn main() {
+//!     let mut port = SimulatedBus::new();
+//!     port.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     port.add_servo(SimulatedDynamixel::new(2, builtin_model(12).unwrap()));
+//!     let bus = DynamixelBus::new(port);
+//!
+//!     let other_bus = bus.clone();
+//!     let other = thread::spawn(move || {
+//!         let mut servo = other_bus.servo(2);
+//!         servo.transaction(|dxl| dxl.ping().map(|status| status.id))
+//!     });
+//!
+//!     let mut servo = bus.servo(1);
+//!     assert_eq!(servo.transaction(|dxl| dxl.detect_model()).unwrap(), 12);
+//!     assert_eq!(other.join().unwrap().unwrap(), 2);
+//! }
+//! ```
+
+use super::capture::{Capture, Direction, RecordingReader};
+use super::protocol_one::{self, PacketDecoder, PacketType};
+use super::reliability::{RetryPolicy, Statistics, DEFAULT_BAUD_RATE, DEFAULT_RETURN_DELAY};
+use super::servo_connection::{drain, read_packet, write_packet, DeadlineReader};
+use super::{BulkReadPacket, Dynamixel, DynamixelError, DynamixelID, Packet, SyncPacket};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The port shared by every handle to the bus. The transaction lock is held
+/// for an entire exchange of packets, while the port itself is only locked
+/// for each individual read or write.
+struct SharedPort<C> {
+    port: Mutex<C>,
+    transaction: Mutex<()>,
+    statistics: Statistics,
+    timing: Timing,
+    capture: Mutex<Option<Capture>>,
+}
+
+impl<C> SharedPort<C> {
+    /// Records bytes sent or received on the bus, if it is being captured.
+    /// Bytes received by consecutive reads are recorded together, as the
+    /// response to the last instruction sent.
+    fn record(&self, direction: Direction, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+
+        if let Some(capture) = lock(&self.capture).as_mut() {
+            match capture.packets.last_mut() {
+                Some(last)
+                    if direction == Direction::Received
+                        && last.direction == Direction::Received =>
+                {
+                    last.bytes.extend(bytes)
+                }
+                _ => capture.record(direction, bytes.to_vec()),
+            }
+        }
+    }
+}
+
+/// How long status packets are waited for on the bus, and how often
+/// instructions are resent
+#[derive(Clone, Copy, Debug)]
+struct Timing {
+    baud_rate: Option<u32>,
+    retry_policy: RetryPolicy,
+}
+
+impl Timing {
+    /// The time to wait for status packets of `response` bytes in total
+    /// after sending an instruction of `request` bytes, when each of
+    /// `servos` waits for its return delay in turn
+    fn timeout(&self, servos: usize, request: usize, response: usize) -> Duration {
+        self.retry_policy.timeout(
+            self.baud_rate.unwrap_or(DEFAULT_BAUD_RATE),
+            DEFAULT_RETURN_DELAY * servos as u32,
+            request,
+            response,
+        )
+    }
+}
+
+/// Locks a mutex, recovering it if a previous holder panicked. A panic part
+/// way through a transaction leaves the port usable, as the decoder of each
+/// servo resynchronises on the next packet.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// A serial port shared between all of the servos connected to it
+pub struct DynamixelBus<C: Read + Write> {
+    shared: Arc<SharedPort<C>>,
+}
+
+impl<C> Clone for DynamixelBus<C>
+where
+    C: Read + Write,
+{
+    fn clone(&self) -> Self {
+        DynamixelBus {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<C> DynamixelBus<C>
+where
+    C: Read + Write,
+{
+    /// Creates a bus which takes ownership of the port, using the default
+    /// retry policy & assuming the default baud rate
+    pub fn new(port: C) -> Self {
+        DynamixelBus::build(port, None, RetryPolicy::default())
+    }
+
+    /// Creates a bus which takes ownership of a port running at the given
+    /// baud rate, waiting for status packets & resending instructions
+    /// according to the retry policy
+    pub fn with_timing(port: C, baud_rate: u32, retry_policy: RetryPolicy) -> Self {
+        DynamixelBus::build(port, Some(baud_rate), retry_policy)
+    }
+
+    fn build(port: C, baud_rate: Option<u32>, retry_policy: RetryPolicy) -> Self {
+        DynamixelBus {
+            shared: Arc::new(SharedPort {
+                port: Mutex::new(port),
+                transaction: Mutex::new(()),
+                statistics: Statistics::new(),
+                timing: Timing {
+                    baud_rate,
+                    retry_policy,
+                },
+                capture: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Creates a handle to the servo with the given ID. Handles are
+    /// independent of each other, so the control table of a servo is only
+    /// known to the handle which loaded it.
+    pub fn servo(&self, id: u8) -> BusServo<C> {
+        self.handle(DynamixelID::ID(id))
+    }
+
+    /// Creates a handle which broadcasts instructions to every servo on the
+    /// bus, without waiting for any status packets
+    pub fn broadcast(&self) -> BusServo<C> {
+        self.handle(DynamixelID::Broadcast)
+    }
+
+    /// The statistics of every servo on the bus, which are shared by every
+    /// handle
+    pub fn statistics(&self) -> &Statistics {
+        &self.shared.statistics
+    }
+
+    /// Starts recording every packet sent & received on the bus, replacing
+    /// any capture already in progress
+    pub fn start_capture(&self) {
+        *lock(&self.shared.capture) = Some(Capture::new());
+    }
+
+    /// Stops recording the packets on the bus, returning those recorded since
+    /// the capture was started
+    pub fn stop_capture(&self) -> Option<Capture> {
+        lock(&self.shared.capture).take()
+    }
+
+    /// Gives exclusive access to the port for the duration of `f`, for
+    /// instructions addressing several servos at once. This must not be
+    /// called from within a servo's transaction, as the bus is already locked.
+    pub fn transaction<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut C) -> T,
+    {
+        let _transaction = lock(&self.shared.transaction);
+        let mut port = lock(&self.shared.port);
+
+        f(&mut port)
+    }
+
+    /// Holds the bus for the duration of `f` without locking the port, so
+    /// that several servos can be communicated with through their handles
+    /// (see [`BusServo::held`]) without any other servo using the bus in
+    /// between
+    pub(crate) fn hold<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let _transaction = lock(&self.shared.transaction);
+
+        f()
+    }
+
+    /// Writes to multiple protocol 1 servos at once with a single packet. As
+    /// the packet is broadcast, no servo sends a status packet in response.
+    /// See [`protocol_one::sync_write`] for the requirements of `packets`.
+    ///
+    /// ```
+    /// use movement::dynamixel::bus::DynamixelBus;
+    /// use movement::dynamixel::control_table::builtin_model;
+    /// use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+    /// use movement::dynamixel::SyncPacket;
+    ///
+    /// fn main() {
+    ///     let mut port = SimulatedBus::new();
+    ///     port.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+    ///     port.add_servo(SimulatedDynamixel::new(2, builtin_model(12).unwrap()));
+    ///     let bus = DynamixelBus::new(port);
+    ///
+    ///     // Turn on the LED of both servos
+    ///     let packets = vec![
+    ///         SyncPacket { id: 1, data: 1, address: 25 },
+    ///         SyncPacket { id: 2, data: 1, address: 25 },
+    ///     ];
+    ///     bus.sync_write(packets, 1).unwrap();
+    ///
+    ///     bus.transaction(|port| {
+    ///         assert_eq!(port.servo(1).unwrap().get_item("LED"), Some(1));
+    ///         assert_eq!(port.servo(2).unwrap().get_item("LED"), Some(1));
+    ///     });
+    /// }
+    /// ```
+    pub fn sync_write(
+        &self,
+        packets: Vec<SyncPacket>,
+        bytesize: usize,
+    ) -> Result<(), DynamixelError> {
+        let packet = match protocol_one::sync_write(packets, bytesize) {
+            Ok(Packet::ProtocolOne(packet)) => packet,
+            Ok(Packet::ProtocolTwo(_)) => unreachable!(),
+            Err(reason) => return Err(DynamixelError::InvalidPacket(reason)),
+        };
+
+        let sent = self.transaction(|port| write_packet(port, packet))?;
+        self.shared.record(Direction::Sent, &sent);
+
+        Ok(())
+    }
+
+    /// Reads from multiple protocol 1 servos (MX series only) with a single
+    /// packet, returning the data read from each servo by ID. A servo which
+    /// fails to respond, or responds with an error, has its error stored in
+    /// place of its data; the `Err` value is only returned if the packet
+    /// could not be sent.
+    ///
+    /// As each servo waits for the servo before it to respond, every servo
+    /// after one which fails to respond times out as well. Servos which time
+    /// out or send a malformed packet are read again according to the retry
+    /// policy of the bus.
+    ///
+    /// ```
+    /// use movement::dynamixel::bus::DynamixelBus;
+    /// use movement::dynamixel::control_table::builtin_model;
+    /// use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+    /// use movement::dynamixel::{BulkReadPacket, DynamixelError};
+    ///
+    /// fn main() {
+    ///     let mut port = SimulatedBus::new();
+    ///     port.add_servo(SimulatedDynamixel::new(1, builtin_model(29).unwrap()));
+    ///     port.add_servo(SimulatedDynamixel::new(2, builtin_model(29).unwrap()));
+    ///     let bus = DynamixelBus::new(port);
+    ///
+    ///     // Read the present position of servo 1 & the model number of servo 2
+    ///     let packets = vec![
+    ///         BulkReadPacket { id: 1, length: 2, address: 36 },
+    ///         BulkReadPacket { id: 2, length: 2, address: 0 },
+    ///         BulkReadPacket { id: 3, length: 1, address: 43 },
+    ///     ];
+    ///     let results = bus.bulk_read(packets).unwrap();
+    ///
+    ///     assert_eq!(results[&1].as_ref().unwrap(), &vec![0x00, 0x08]);
+    ///     assert_eq!(results[&2].as_ref().unwrap(), &vec![29, 0]);
+    ///     assert!(matches!(results[&3], Err(DynamixelError::Timeout)));
+    /// }
+    /// ```
+    pub fn bulk_read(
+        &self,
+        packets: Vec<BulkReadPacket>,
+    ) -> Result<HashMap<u8, Result<Vec<u8>, DynamixelError>>, DynamixelError> {
+        let mut pending = packets;
+        let retries = self.shared.timing.retry_policy.retries;
+
+        self.transaction(|port| {
+            let mut decoder = PacketDecoder::new();
+            let mut results = HashMap::new();
+
+            for attempt in 0..=retries {
+                let attempt_results = self.bulk_read_once(port, &mut decoder, &pending)?;
+                for (id, result) in attempt_results.iter() {
+                    self.shared.statistics.record(*id, |servo| {
+                        servo.sent += 1;
+                        if attempt > 0 {
+                            servo.retries += 1;
+                        }
+                        match result {
+                            Ok(_) => servo.received += 1,
+                            Err(DynamixelError::Timeout) => servo.timeouts += 1,
+                            Err(DynamixelError::Status(_)) => {
+                                servo.received += 1;
+                                servo.servo_errors += 1;
+                            }
+                            Err(_) => servo.received += 1,
+                        }
+                    });
+                }
+                results.extend(attempt_results);
+
+                // Only the servos which did not respond properly are read again
+                pending.retain(|packet| {
+                    matches!(&results[&packet.id], Err(err) if RetryPolicy::should_retry(err))
+                });
+                if pending.is_empty() {
+                    break;
+                }
+            }
+
+            Ok(results)
+        })
+    }
+
+    /// Sends a single bulk read, waiting for the status packet of each servo
+    fn bulk_read_once(
+        &self,
+        port: &mut C,
+        decoder: &mut PacketDecoder,
+        packets: &[BulkReadPacket],
+    ) -> Result<HashMap<u8, Result<Vec<u8>, DynamixelError>>, DynamixelError> {
+        let lengths: HashMap<u8, usize> = packets
+            .iter()
+            .map(|packet| (packet.id, packet.length as usize))
+            .collect();
+        let copies = packets.iter().map(|packet| BulkReadPacket {
+            id: packet.id,
+            length: packet.length,
+            address: packet.address,
+        });
+        let packet = match protocol_one::bulk_read(copies.collect()) {
+            Ok(Packet::ProtocolOne(packet)) => packet,
+            Ok(Packet::ProtocolTwo(_)) => unreachable!(),
+            Err(reason) => return Err(DynamixelError::InvalidPacket(reason)),
+        };
+
+        // Each servo responds once the servo before it has responded
+        let response = lengths.values().map(|length| length + 6).sum();
+        let timeout =
+            self.shared
+                .timing
+                .timeout(lengths.len(), packet.parameters.len() + 6, response);
+
+        decoder.clear();
+        let sent = write_packet(port, packet)?;
+        decoder.expect_echo(&sent);
+        self.shared.record(Direction::Sent, &sent);
+
+        let mut connection = RecordingReader::new(DeadlineReader::new(port, timeout));
+        let mut results = HashMap::new();
+        while results.len() < lengths.len() {
+            let status = match read_packet(&mut connection, decoder) {
+                Ok(status) => status,
+                Err(DynamixelError::Io(err)) => return Err(DynamixelError::Io(err)),
+                Err(_) => {
+                    drain(&mut connection, timeout)?;
+                    break;
+                }
+            };
+
+            let length = match lengths.get(&status.id) {
+                Some(length) if !results.contains_key(&status.id) => *length,
+                _ => continue,
+            };
+
+            let result = match status.packet_type {
+                PacketType::Status(ref errors) if !errors.is_empty() => {
+                    Err(DynamixelError::Status(errors.clone()))
+                }
+                _ if status.parameters.len() != length => Err(DynamixelError::Packet(
+                    protocol_one::PacketReadError::InvalidLength,
+                )),
+                _ => Ok(status.parameters),
+            };
+            results.insert(status.id, result);
+        }
+
+        self.shared
+            .record(Direction::Received, &connection.into_bytes());
+
+        // Any servo yet to respond has timed out
+        for id in lengths.keys() {
+            results.entry(*id).or_insert(Err(DynamixelError::Timeout));
+        }
+
+        Ok(results)
+    }
+
+    fn handle(&self, id: DynamixelID) -> BusServo<C> {
+        let connection = BusConnection {
+            shared: Arc::clone(&self.shared),
+        };
+
+        let mut dynamixel = Dynamixel::new_empty(connection, id);
+        dynamixel.statistics = self.shared.statistics.clone();
+        dynamixel.baud_rate = self.shared.timing.baud_rate;
+        dynamixel.retry_policy = self.shared.timing.retry_policy;
+
+        BusServo {
+            dynamixel,
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+/// The connection used by servos on a bus, which reads from & writes to the
+/// shared port
+pub struct BusConnection<C> {
+    shared: Arc<SharedPort<C>>,
+}
+
+impl<C: Read> Read for BusConnection<C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = lock(&self.shared.port).read(buf)?;
+        self.shared.record(Direction::Received, &buf[..length]);
+
+        Ok(length)
+    }
+}
+
+impl<C: Write> Write for BusConnection<C> {
+    /// Writes the whole of `buf` at once, so that each instruction is
+    /// captured as a single packet
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        lock(&self.shared.port).write_all(buf)?;
+        self.shared.record(Direction::Sent, buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        lock(&self.shared.port).flush()
+    }
+}
+
+/// A handle to a single servo on a shared bus
+pub struct BusServo<C: Read + Write> {
+    dynamixel: Dynamixel<BusConnection<C>>,
+    shared: Arc<SharedPort<C>>,
+}
+
+impl<C> BusServo<C>
+where
+    C: Read + Write,
+{
+    /// The ID addressed by the handle
+    pub fn id(&self) -> DynamixelID {
+        self.dynamixel.id
+    }
+
+    /// Communicates with the servo, holding the bus for the duration of `f`
+    /// so that no other servo may use it until every status packet has been
+    /// received
+    pub fn transaction<F, T>(&mut self, f: F) -> T
+    where
+        F: FnOnce(&mut Dynamixel<BusConnection<C>>) -> T,
+    {
+        let _transaction = lock(&self.shared.transaction);
+
+        f(&mut self.dynamixel)
+    }
+
+    /// The servo, without holding the bus. This must only be used while the
+    /// bus is already held by [`DynamixelBus::hold`].
+    pub(crate) fn held(&mut self) -> &mut Dynamixel<BusConnection<C>> {
+        &mut self.dynamixel
+    }
+}
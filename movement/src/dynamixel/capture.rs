@@ -0,0 +1,421 @@
+//! # Packet capture & replay
+//! When `collects_packets` is set, a `Dynamixel` records every instruction it
+//! sends & every byte it receives in its [`Capture`], along with the time
+//! & direction of each. The instructions themselves are also kept in
+//! `sent_packets`, and the last status packet in `last_packet`.
+//!
+//! Sync writes, bulk reads & coordinated motions are sent by a `DynamixelBus`
+//! rather than a single `Dynamixel`, so are recorded by capturing the whole
+//! bus instead (see `DynamixelBus::start_capture`).
+//!
+//! Captures are saved as TOML, with the bytes of each packet written in hex,
+//! so that they can be loaded back later. A [`ReplayConnection`] then serves
+//! the recorded responses to the same instructions, reproducing a run of the
+//! robot without any servos attached.
+//!
+//! ```
+//! use movement::dynamixel::capture::{Capture, Direction, ReplayConnection};
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID};
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!     dxl.collects_packets = true;
+//!     dxl.detect_model().unwrap();
+//!     let temperature = dxl.read_item("Present Temperature").unwrap();
+//!
+//!     let capture = &dxl.capture;
+//!     assert_eq!(capture.packets[0].direction, Direction::Sent);
+//!     assert_eq!(capture.packets[0].bytes, [0xFF, 0xFF, 0x01, 0x04, 0x02, 0x00, 0x02, 0xF6]);
+//!     assert_eq!(dxl.sent_packets.len(), 2);
+//!
+//!     // The same instructions receive the same responses when replayed
+//!     let saved = capture.to_toml().unwrap();
+//!     let replay = ReplayConnection::new(Capture::from_toml(&saved).unwrap());
+//!     let mut dxl = Dynamixel::new_empty(replay, DynamixelID::ID(1));
+//!     dxl.detect_model().unwrap();
+//!     assert_eq!(dxl.read_item("Present Temperature").unwrap(), temperature);
+//!     assert_eq!(dxl.connection_handler.remaining(), 0);
+//!
+//!     // Any instruction which was not recorded fails
+//!     assert!(dxl.read_item("Present Voltage").is_err());
+//! }
+//! ```
+
+use super::dissector::to_hex;
+use super::servo_connection::BaudRate;
+use super::{Dynamixel, DynamixelError, Packet};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The ways in which a capture can fail to be saved or loaded
+#[derive(Debug)]
+pub enum CaptureError {
+    /// The file could not be read or written
+    Io(std::io::Error),
+    /// The capture is not valid TOML, or is missing required fields
+    Parse(String),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CaptureError::Io(err) => write!(f, "unable to access capture: {}", err),
+            CaptureError::Parse(err) => write!(f, "unable to parse capture: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+/// Whether a packet was sent to or received from the servos
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// The bytes of a single instruction sent, or the bytes received in response
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedPacket {
+    /// Microseconds since the capture started
+    pub time: u64,
+    pub direction: Direction,
+    #[serde(with = "hex")]
+    pub bytes: Vec<u8>,
+}
+
+/// Every packet exchanged with the servos, in the order they were exchanged
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Capture {
+    /// Seconds since the Unix epoch at which the capture started
+    pub started: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packets: Vec<CapturedPacket>,
+    #[serde(skip, default = "Instant::now")]
+    start: Instant,
+}
+
+impl Default for Capture {
+    fn default() -> Capture {
+        Capture::new()
+    }
+}
+
+impl Capture {
+    /// Starts an empty capture
+    pub fn new() -> Capture {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+
+        Capture {
+            started,
+            packets: vec![],
+            start: Instant::now(),
+        }
+    }
+
+    /// Records bytes exchanged with the servos at the current time
+    pub fn record(&mut self, direction: Direction, bytes: Vec<u8>) {
+        self.packets.push(CapturedPacket {
+            time: self.start.elapsed().as_micros() as u64,
+            direction,
+            bytes,
+        });
+    }
+
+    /// Parses a capture saved as TOML
+    ///
+    /// ```
+    /// use movement::dynamixel::capture::{Capture, Direction};
+    ///
+    /// fn main() {
+    ///     let capture = Capture::from_toml(
+    ///         r#"
+    ///         started = 1600000000
+    ///
+    ///         [[packets]]
+    ///         time = 0
+    ///         direction = "Sent"
+    ///         bytes = "FF FF 01 02 01 FB"
+    ///
+    ///         [[packets]]
+    ///         time = 1250
+    ///         direction = "Received"
+    ///         bytes = "FF FF 01 02 00 FC"
+    ///         "#,
+    ///     )
+    ///     .unwrap();
+    ///
+    ///     assert_eq!(capture.packets[1].direction, Direction::Received);
+    ///     assert_eq!(capture.packets[1].bytes, [0xFF, 0xFF, 0x01, 0x02, 0x00, 0xFC]);
+    /// }
+    /// ```
+    pub fn from_toml(source: &str) -> Result<Capture, CaptureError> {
+        toml::from_str(source).map_err(|err| CaptureError::Parse(err.to_string()))
+    }
+
+    /// Reads & parses a capture from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Capture, CaptureError> {
+        let source = std::fs::read_to_string(path).map_err(CaptureError::Io)?;
+        Capture::from_toml(&source)
+    }
+
+    /// Serialises the capture as TOML
+    pub fn to_toml(&self) -> Result<String, CaptureError> {
+        toml::to_string(self).map_err(|err| CaptureError::Parse(err.to_string()))
+    }
+
+    /// Saves the capture to a TOML file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), CaptureError> {
+        std::fs::write(path, self.to_toml()?).map_err(CaptureError::Io)
+    }
+}
+
+/// Saves a capture as each packet is recorded, so that nothing is lost if
+/// capturing is stopped part way through. Each packet is appended to the
+/// file as a TOML table, so that the file can be loaded at any time.
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Starts a capture, writing its header to the writer
+    pub fn new(mut writer: W) -> Result<CaptureWriter<W>, CaptureError> {
+        let capture = Capture::new();
+        writer
+            .write_all(capture.to_toml()?.as_bytes())
+            .map_err(CaptureError::Io)?;
+
+        Ok(CaptureWriter {
+            writer,
+            start: capture.start,
+        })
+    }
+
+    /// Appends bytes exchanged with the servos at the current time
+    pub fn record(&mut self, direction: Direction, bytes: Vec<u8>) -> Result<(), CaptureError> {
+        self.record_at(self.start.elapsed(), direction, bytes)
+    }
+
+    /// Appends bytes exchanged with the servos at the given time since the
+    /// capture started, such as the time a sniffed packet was read
+    pub fn record_at(
+        &mut self,
+        time: Duration,
+        direction: Direction,
+        bytes: Vec<u8>,
+    ) -> Result<(), CaptureError> {
+        #[derive(Serialize)]
+        struct Table {
+            packets: Vec<CapturedPacket>,
+        }
+
+        let table = Table {
+            packets: vec![CapturedPacket {
+                time: time.as_micros() as u64,
+                direction,
+                bytes,
+            }],
+        };
+        let toml = toml::to_string(&table).map_err(|err| CaptureError::Parse(err.to_string()))?;
+
+        writeln!(self.writer)
+            .and_then(|_| self.writer.write_all(toml.as_bytes()))
+            .and_then(|_| self.writer.flush())
+            .map_err(CaptureError::Io)
+    }
+}
+
+/// Writes bytes as space separated pairs of hex digits
+mod hex {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        String::deserialize(deserializer)?
+            .split_whitespace()
+            .map(|byte| u8::from_str_radix(byte, 16).map_err(de::Error::custom))
+            .collect()
+    }
+}
+
+/// A connection which replays a capture. Each instruction written must match
+/// the next instruction sent in the capture, after which the bytes received
+/// in response to it can be read back. Reading reports the end of the stream
+/// when there is no response, as the servo did not respond when recorded.
+pub struct ReplayConnection {
+    packets: VecDeque<CapturedPacket>,
+    written: Vec<u8>,
+    output: VecDeque<u8>,
+}
+
+impl ReplayConnection {
+    /// Creates a connection which replays the packets of the capture
+    pub fn new(capture: Capture) -> ReplayConnection {
+        let mut connection = ReplayConnection {
+            packets: capture.packets.into(),
+            written: vec![],
+            output: VecDeque::new(),
+        };
+        connection.queue_responses();
+
+        connection
+    }
+
+    /// The number of recorded packets yet to be replayed
+    pub fn remaining(&self) -> usize {
+        self.packets.len()
+    }
+
+    /// Makes every packet received before the next instruction available to
+    /// be read
+    fn queue_responses(&mut self) {
+        while let Some(packet) = self.packets.front() {
+            if packet.direction == Direction::Sent {
+                break;
+            }
+
+            self.output.extend(&packet.bytes);
+            self.packets.pop_front();
+        }
+    }
+}
+
+impl Write for ReplayConnection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend(buf);
+
+        while !self.written.is_empty() {
+            let expected = match self.packets.front() {
+                Some(packet) => &packet.bytes,
+                None => {
+                    self.written.clear();
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "every instruction in the capture has been replayed",
+                    ));
+                }
+            };
+
+            let length = self.written.len().min(expected.len());
+            if self.written[..length] != expected[..length] {
+                let error = format!(
+                    "expected instruction {:02X?} from the capture, but {:02X?} was written",
+                    expected, self.written
+                );
+                self.written.clear();
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+
+            // Wait for the rest of the instruction to be written
+            if length < expected.len() {
+                break;
+            }
+
+            self.written.drain(..length);
+            self.packets.pop_front();
+            self.queue_responses();
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for ReplayConnection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = buf.len().min(self.output.len());
+        for (byte, output) in buf.iter_mut().zip(self.output.drain(..length)) {
+            *byte = output;
+        }
+
+        Ok(length)
+    }
+}
+
+impl BaudRate for ReplayConnection {
+    /// The baud rate has no effect on a replay
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> Result<(), DynamixelError> {
+        Ok(())
+    }
+}
+
+/// A reader which keeps a copy of every byte read through it
+pub(crate) struct RecordingReader<R> {
+    connection: R,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> RecordingReader<R> {
+    pub(crate) fn new(connection: R) -> Self {
+        RecordingReader {
+            connection,
+            bytes: vec![],
+        }
+    }
+
+    /// The bytes which have been read
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self.connection.read(buf)?;
+        self.bytes.extend(&buf[..length]);
+
+        Ok(length)
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Records an instruction sent to the servo, if packets are collected
+    pub(crate) fn record_sent<P>(&mut self, bytes: &[u8], packet: &P)
+    where
+        P: Clone + Into<Packet>,
+    {
+        if self.collects_packets {
+            self.capture.record(Direction::Sent, bytes.to_vec());
+            self.sent_packets.push(packet.clone().into());
+        }
+    }
+
+    /// Records the bytes received from the servo, along with the status
+    /// packet decoded from them, if packets are collected
+    pub(crate) fn record_received<P>(&mut self, bytes: Vec<u8>, packet: Option<&P>)
+    where
+        P: Clone + Into<Packet>,
+    {
+        if !self.collects_packets {
+            return;
+        }
+
+        if !bytes.is_empty() {
+            self.capture.record(Direction::Received, bytes);
+        }
+        if let Some(packet) = packet {
+            self.last_packet = Some(packet.clone().into());
+        }
+    }
+}
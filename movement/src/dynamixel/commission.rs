@@ -0,0 +1,298 @@
+//! # Commissioning
+//! Servos arrive from the factory sharing the same ID, so each must be given
+//! its own ID (and often a faster baud rate) before it joins a robot. This
+//! file changes the ID & baud rate of protocol 1 servos safely: the new ID
+//! is checked to be free before it is written, and the servo is pinged at its
+//! new ID & baud rate afterwards to check that the change took effect.
+//!
+//! The status packet confirming a change may come from the new ID, or at the
+//! new baud rate, or not at all, so it is not relied upon. Instead, the
+//! change is only reported as successful once the servo responds to a ping.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelError, DynamixelID};
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     bus.add_servo(SimulatedDynamixel::new(5, builtin_model(12).unwrap()));
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!
+//!     // ID 5 is taken by the other servo
+//!     assert!(matches!(dxl.change_id(5), Err(DynamixelError::IDInUse(5))));
+//!
+//!     dxl.change_id(7).unwrap();
+//!     assert_eq!(dxl.id, DynamixelID::ID(7));
+//!     assert!(dxl.connection_handler.servo(7).is_some());
+//!
+//!     dxl.change_baud_rate(57_600).unwrap();
+//!     assert_eq!(dxl.connection_handler.servo(7).unwrap().get_item("Baud Rate"), Some(34));
+//! }
+//! ```
+//!
+//! Several servos can be commissioned in turn with [`assign_batch`], which
+//! finds each new servo as the operator connects it.
+
+use super::protocol_one::{self, ProtocolOne};
+use super::scan::{scan, FoundServo, ScanOptions};
+use super::servo_connection::BaudRate;
+use super::{DataBytes, Dynamixel, DynamixelError, DynamixelID, Protocol};
+use std::io::{Read, Write};
+
+/// The address of the ID item on every protocol 1 servo
+const ID_ADDRESS: u8 = 3;
+
+/// The address of the Baud Rate item on every protocol 1 servo
+const BAUD_RATE_ADDRESS: u8 = 4;
+
+/// The highest ID a servo may be given, as 254 is the broadcast ID
+const MAX_ID: u8 = 253;
+
+/// A servo which was commissioned by [`assign_batch`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Assignment {
+    /// The servo as it was found on the bus
+    pub found: FoundServo,
+    /// The ID the servo was given
+    pub id: u8,
+    /// The baud rate the servo now communicates at
+    pub baud_rate: u32,
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Changes the ID of the servo, after checking that no other servo on
+    /// the bus responds to the new ID. The servo is addressed by its new ID
+    /// once it has responded to a ping there.
+    pub fn change_id(&mut self, new_id: u8) -> Result<(), DynamixelError> {
+        if new_id > MAX_ID {
+            return Err(DynamixelError::OutOfRange {
+                name: "ID".to_string(),
+                value: new_id as i64,
+            });
+        }
+
+        let id = match self.id {
+            DynamixelID::ID(id) => id,
+            DynamixelID::Broadcast => return Err(DynamixelError::Broadcast),
+        };
+        if id == new_id {
+            return Ok(());
+        }
+
+        ProtocolOne::ping(self)?;
+        if self.responds_at(new_id)? {
+            return Err(DynamixelError::IDInUse(new_id));
+        }
+
+        match ProtocolOne::write(self, ID_ADDRESS, DataBytes::One(new_id)) {
+            Ok(_) | Err(DynamixelError::Timeout) => {}
+            Err(DynamixelError::IDMismatch { found, .. }) if found == new_id => {}
+            Err(err) => return Err(err),
+        }
+
+        self.id = DynamixelID::ID(new_id);
+        self.verify()
+    }
+
+    /// Checks whether any servo responds to a ping at the given ID
+    pub(crate) fn responds_at(&mut self, id: u8) -> Result<bool, DynamixelError> {
+        let current = std::mem::replace(&mut self.id, DynamixelID::ID(id));
+        let result = ProtocolOne::ping(self);
+        self.id = current;
+
+        // Anything other than silence means the ID is in use
+        match result {
+            Ok(_) => Ok(true),
+            Err(DynamixelError::Timeout) => Ok(false),
+            Err(DynamixelError::Io(err)) => Err(DynamixelError::Io(err)),
+            Err(_) => Ok(true),
+        }
+    }
+
+    /// Pings the servo at its ID & the baud rate of the connection
+    pub(crate) fn verify(&mut self) -> Result<(), DynamixelError> {
+        match ProtocolOne::ping(self) {
+            Ok(_) | Err(DynamixelError::Status(_)) => Ok(()),
+            Err(DynamixelError::Io(err)) => Err(DynamixelError::Io(err)),
+            Err(_) => Err(DynamixelError::Unverified {
+                id: self.id.into(),
+                baud_rate: self.baud_rate,
+            }),
+        }
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write + BaudRate,
+{
+    /// Changes the baud rate of the servo to the closest supported speed,
+    /// then switches the connection to it. The change is refused if another
+    /// servo with the same ID already communicates at the new baud rate, and
+    /// is only reported as successful once the servo responds to a ping at
+    /// the new baud rate.
+    pub fn change_baud_rate(&mut self, bits_per_second: u32) -> Result<(), DynamixelError> {
+        let value =
+            protocol_one::baud_rate_value(bits_per_second).ok_or(DynamixelError::OutOfRange {
+                name: "Baud Rate".to_string(),
+                value: bits_per_second as i64,
+            })?;
+        let new_rate = protocol_one::baud_rate(value).unwrap_or(bits_per_second);
+
+        ProtocolOne::ping(self)?;
+        let current = match self.baud_rate {
+            Some(current) => current,
+            None => {
+                let value = ProtocolOne::read(self, BAUD_RATE_ADDRESS, 1)?.parameters[0];
+                protocol_one::baud_rate(value).ok_or(DynamixelError::OutOfRange {
+                    name: "Baud Rate".to_string(),
+                    value: value as i64,
+                })?
+            }
+        };
+        if current == new_rate {
+            return Ok(());
+        }
+
+        // Look for a servo with the same ID at the new baud rate
+        let id = self.id.into();
+        BaudRate::set_baud_rate(self.connection_handler.as_mut(), new_rate)?;
+        let collides = self.responds_at(id);
+        BaudRate::set_baud_rate(self.connection_handler.as_mut(), current)?;
+        if collides? {
+            return Err(DynamixelError::IDInUse(id));
+        }
+
+        // The response may be sent at either baud rate, so is garbled or lost
+        match ProtocolOne::write(self, BAUD_RATE_ADDRESS, DataBytes::One(value)) {
+            Ok(_) | Err(DynamixelError::Timeout) | Err(DynamixelError::Packet(_)) => {}
+            Err(err) => return Err(err),
+        }
+        self.decoder = protocol_one::PacketDecoder::new();
+
+        BaudRate::set_baud_rate(self.connection_handler.as_mut(), new_rate)?;
+        self.baud_rate = Some(new_rate);
+        self.verify()
+    }
+}
+
+/// Commissions servos one at a time as the operator connects them, giving
+/// each the next ID from `ids` and optionally changing its baud rate.
+///
+/// Before each servo is searched for, `ready` is called with the ID it will
+/// be given, along with the error which stopped the previous attempt to
+/// commission it, if any. The operator should connect the servo before
+/// `ready` returns `true`, or return `false` to stop the batch. The servos
+/// already commissioned may be left connected, as long as the new servo does
+/// not share an ID with any of them; only protocol 1 servos are searched for.
+///
+/// ```
+/// use movement::dynamixel::commission::assign_batch;
+/// use movement::dynamixel::control_table::builtin_model;
+/// use movement::dynamixel::scan::ScanOptions;
+/// use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+/// use movement::dynamixel::Protocol;
+///
+/// fn main() {
+///     let mut bus = SimulatedBus::new();
+///     let options = ScanOptions {
+///         ids: 0..=10,
+///         baud_rates: vec![1_000_000],
+///         protocols: vec![Protocol::One],
+///     };
+///
+///     // Each servo arrives with the factory ID of 1
+///     let assigned = assign_batch(&mut bus, &[3, 4], &options, None, |bus, _, _| {
+///         bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+///         true
+///     })
+///     .unwrap();
+///
+///     assert_eq!(assigned.len(), 2);
+///     assert!(bus.servo(3).is_some() && bus.servo(4).is_some());
+///     assert!(bus.servo(1).is_none());
+/// }
+/// ```
+pub fn assign_batch<C, F>(
+    connection: &mut C,
+    ids: &[u8],
+    options: &ScanOptions,
+    baud_rate: Option<u32>,
+    mut ready: F,
+) -> Result<Vec<Assignment>, DynamixelError>
+where
+    C: Read + Write + BaudRate,
+    F: FnMut(&mut C, u8, Option<&DynamixelError>) -> bool,
+{
+    let options = ScanOptions {
+        protocols: vec![Protocol::One],
+        ..options.clone()
+    };
+    let mut assigned: Vec<Assignment> = vec![];
+
+    for &id in ids {
+        let mut error = None;
+        loop {
+            if !ready(connection, id, error.as_ref()) {
+                return Ok(assigned);
+            }
+
+            match assign(connection, id, &options, baud_rate, &assigned) {
+                Ok(assignment) => {
+                    assigned.push(assignment);
+                    break;
+                }
+                Err(DynamixelError::Io(err)) => return Err(DynamixelError::Io(err)),
+                Err(err) => error = Some(err),
+            }
+        }
+    }
+
+    Ok(assigned)
+}
+
+/// Finds the single servo which has not yet been commissioned, then gives it
+/// the ID & baud rate
+fn assign<C>(
+    connection: &mut C,
+    id: u8,
+    options: &ScanOptions,
+    baud_rate: Option<u32>,
+    assigned: &[Assignment],
+) -> Result<Assignment, DynamixelError>
+where
+    C: Read + Write + BaudRate,
+{
+    let mut found: Vec<FoundServo> = scan(connection, options, |_| {})?
+        .into_iter()
+        .filter(|servo| {
+            !assigned
+                .iter()
+                .any(|other| other.id == servo.id && other.baud_rate == servo.baud_rate)
+        })
+        .collect();
+    if found.len() != 1 {
+        return Err(DynamixelError::ServoCount(found.len()));
+    }
+    let found = found.remove(0);
+
+    connection.set_baud_rate(found.baud_rate)?;
+    let mut dxl = Dynamixel::new_empty(&mut *connection, DynamixelID::ID(found.id));
+    dxl.baud_rate = Some(found.baud_rate);
+    dxl.change_id(id)?;
+    if let Some(baud_rate) = baud_rate {
+        dxl.change_baud_rate(baud_rate)?;
+    }
+
+    Ok(Assignment {
+        id,
+        baud_rate: dxl.baud_rate.unwrap_or(found.baud_rate),
+        found,
+    })
+}
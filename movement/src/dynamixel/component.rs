@@ -0,0 +1,222 @@
+//! # Components
+//! Components are the parts of a servo which are actuated without moving it,
+//! such as its LED or torque. Each component is an item in the Component
+//! category of the control table, and is accessed through a typed
+//! [`Component`] handle rather than by reading & writing raw values:
+//!
+//! - `LED` & `Torque Enable` are switches, which are either on or off
+//! - `Alarm LED` & `Shutdown` are the errors which flash the LED or turn off
+//!   the torque of the servo, given as a list of [`StatusType`]
+//! - The compliance margins & slopes are levels between 0 and 255
+//!
+//! The kind of each component of the servo's model is indexed in the
+//! `components` of the `Dynamixel`. Components are read & written using the
+//! protocol of the servo's model, though the errors held by the alarms are
+//! those of protocol 1, so the alarms of protocol 2 servos are rejected with
+//! `DynamixelError::UnsupportedProtocol`.
+//!
+//! ```
+//! use movement::dynamixel::component::ComponentType;
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::{ProtocolOne, StatusType};
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID};
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!     dxl.detect_model().unwrap();
+//!     assert_eq!(dxl.components["Shutdown"], ComponentType::Alarms);
+//!
+//!     dxl.led().toggle().unwrap();
+//!     assert_eq!(dxl.led().get().unwrap(), true);
+//!
+//!     // Only turn off the torque when overheating
+//!     dxl.shutdown().set(vec![StatusType::Overheating]).unwrap();
+//!     assert_eq!(dxl.shutdown().get().unwrap(), vec![StatusType::Overheating]);
+//! }
+//! ```
+
+use super::protocol_one::StatusType;
+use super::{Dynamixel, DynamixelError};
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+
+/// The kinds of value held by a component
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentType {
+    /// Either on (1) or off (0)
+    Switch,
+    /// A bitmask of errors
+    Alarms,
+    /// A raw level, such as a compliance margin
+    Level,
+}
+
+impl ComponentType {
+    /// Gets the kind of a component from the name of its item
+    pub fn of(name: &str) -> ComponentType {
+        match name {
+            "LED" | "Torque Enable" => ComponentType::Switch,
+            "Alarm LED" | "Shutdown" => ComponentType::Alarms,
+            _ => ComponentType::Level,
+        }
+    }
+}
+
+/// A value which can be stored in a component
+pub trait ComponentValue: Sized {
+    /// Whether the value is only meaningful to protocol 1 servos
+    const PROTOCOL_ONE_ONLY: bool = false;
+
+    fn from_raw(raw: u64) -> Self;
+    fn to_raw(&self) -> u64;
+}
+
+impl ComponentValue for bool {
+    fn from_raw(raw: u64) -> bool {
+        raw != 0
+    }
+
+    fn to_raw(&self) -> u64 {
+        *self as u64
+    }
+}
+
+impl ComponentValue for u8 {
+    fn from_raw(raw: u64) -> u8 {
+        raw as u8
+    }
+
+    fn to_raw(&self) -> u64 {
+        *self as u64
+    }
+}
+
+impl ComponentValue for Vec<StatusType> {
+    const PROTOCOL_ONE_ONLY: bool = true;
+
+    fn from_raw(raw: u64) -> Vec<StatusType> {
+        StatusType::get_error_types(&(raw as u8))
+    }
+
+    fn to_raw(&self) -> u64 {
+        StatusType::get_error_code(self) as u64
+    }
+}
+
+/// A handle to a single component of a servo
+pub struct Component<'a, C: Read + Write, T> {
+    dynamixel: &'a mut Dynamixel<C>,
+    name: &'a str,
+    value: PhantomData<T>,
+}
+
+impl<'a, C, T> Component<'a, C, T>
+where
+    C: Read + Write,
+    T: ComponentValue,
+{
+    /// The name of the component's item in the control table
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Reads the value of the component from the servo
+    pub fn get(&mut self) -> Result<T, DynamixelError> {
+        if T::PROTOCOL_ONE_ONLY {
+            self.dynamixel.require_protocol_one("read the alarms")?;
+        }
+
+        Ok(T::from_raw(self.dynamixel.read_model_item(self.name)?))
+    }
+
+    /// Writes a value to the component
+    pub fn set(&mut self, value: T) -> Result<(), DynamixelError> {
+        if T::PROTOCOL_ONE_ONLY {
+            self.dynamixel.require_protocol_one("set the alarms")?;
+        }
+
+        self.dynamixel.write_model_item(self.name, value.to_raw())
+    }
+}
+
+impl<'a, C> Component<'a, C, bool>
+where
+    C: Read + Write,
+{
+    /// Turns the component on
+    pub fn on(&mut self) -> Result<(), DynamixelError> {
+        self.set(true)
+    }
+
+    /// Turns the component off
+    pub fn off(&mut self) -> Result<(), DynamixelError> {
+        self.set(false)
+    }
+
+    /// Reads the state of the component, then switches it to the other
+    pub fn toggle(&mut self) -> Result<(), DynamixelError> {
+        let state = self.get()?;
+        self.set(!state)
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Gets a handle to any component by name, holding values of type `T`
+    pub fn component<'a, T>(&'a mut self, name: &'a str) -> Component<'a, C, T>
+    where
+        T: ComponentValue,
+    {
+        Component {
+            dynamixel: self,
+            name,
+            value: PhantomData,
+        }
+    }
+
+    /// The LED of the servo
+    pub fn led(&mut self) -> Component<'_, C, bool> {
+        self.component("LED")
+    }
+
+    /// Whether the servo holds its position or can be turned freely
+    pub fn torque_enable(&mut self) -> Component<'_, C, bool> {
+        self.component("Torque Enable")
+    }
+
+    /// The errors which cause the LED to flash
+    pub fn alarm_led(&mut self) -> Component<'_, C, Vec<StatusType>> {
+        self.component("Alarm LED")
+    }
+
+    /// The errors which cause the torque to be turned off
+    pub fn shutdown(&mut self) -> Component<'_, C, Vec<StatusType>> {
+        self.component("Shutdown")
+    }
+
+    /// The error allowed before turning clockwise towards the goal position
+    pub fn cw_compliance_margin(&mut self) -> Component<'_, C, u8> {
+        self.component("CW Compliance Margin")
+    }
+
+    /// The error allowed before turning counterclockwise towards the goal
+    /// position
+    pub fn ccw_compliance_margin(&mut self) -> Component<'_, C, u8> {
+        self.component("CCW Compliance Margin")
+    }
+
+    /// The flexibility when approaching the goal position clockwise
+    pub fn cw_compliance_slope(&mut self) -> Component<'_, C, u8> {
+        self.component("CW Compliance Slope")
+    }
+
+    /// The flexibility when approaching the goal position counterclockwise
+    pub fn ccw_compliance_slope(&mut self) -> Component<'_, C, u8> {
+        self.component("CCW Compliance Slope")
+    }
+}
@@ -0,0 +1,509 @@
+//! # Control tables
+//! This file contains the control tables of the Dynamixel models supported
+//! out of the box, as documented in the
+//! [Robotis e-Manual](https://emanual.robotis.com/docs/en/dxl/). Each item
+//! is described by its address, size, access level, range and units.
+//!
+//! Models which are not built in can be described in a TOML file and loaded
+//! at runtime with [`Model::from_file`], for example:
+//!
+//! ```toml
+//! number = 12
+//! name = "AX-12A"
+//!
+//! [[items]]
+//! name = "LED"
+//! category = "Component"
+//! address = 25
+//! size = 1
+//! access = "ReadWrite"
+//! initial_value = "0"
+//! range = [0, 1]
+//! ```
+
+use super::{AccessLevel, ControlTableData, ControlTableType, Protocol};
+use sensor::DataUnit;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The ways in which a control table description can be invalid
+#[derive(Debug)]
+pub enum ControlTableError {
+    /// The description could not be read
+    Io(std::io::Error),
+    /// The description is not valid TOML, or is missing required fields
+    Parse(String),
+    /// An item is not 1, 2 or 4 bytes long
+    InvalidSize { name: String, size: u8 },
+    /// An item's range is reversed or cannot be stored in its size
+    InvalidRange { name: String, range: (i64, i64) },
+    /// An item extends past the last address of the control table
+    InvalidAddress { name: String, address: u16 },
+    /// Two items occupy the same address
+    Overlap { first: String, second: String },
+    /// Two items share the same name
+    DuplicateName(String),
+}
+
+impl std::fmt::Display for ControlTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ControlTableError::Io(err) => write!(f, "unable to read control table: {}", err),
+            ControlTableError::Parse(err) => write!(f, "unable to parse control table: {}", err),
+            ControlTableError::InvalidSize { name, size } => {
+                write!(f, "item {} has an invalid size of {} bytes", name, size)
+            }
+            ControlTableError::InvalidRange { name, range } => {
+                write!(f, "item {} has an invalid range of {:?}", name, range)
+            }
+            ControlTableError::InvalidAddress { name, address } => {
+                write!(
+                    f,
+                    "item {} at address {} runs past the end of the control table",
+                    name, address
+                )
+            }
+            ControlTableError::Overlap { first, second } => {
+                write!(f, "items {} and {} overlap", first, second)
+            }
+            ControlTableError::DuplicateName(name) => {
+                write!(f, "item {} is defined more than once", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ControlTableError {}
+
+/// A single named item in a control table
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ControlTableItem {
+    pub name: String,
+    pub category: ControlTableType,
+    #[serde(flatten)]
+    pub data: ControlTableData,
+}
+
+impl ControlTableItem {
+    /// The address just past the last byte of the item, which may lie beyond
+    /// the 16 bit address space
+    fn end(&self) -> u32 {
+        self.data.address as u32 + self.data.size as u32
+    }
+}
+
+/// The full control table of a Dynamixel model
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Model {
+    pub number: u16,
+    pub name: String,
+    /// The protocol the model communicates with, which is protocol 1 unless
+    /// given otherwise
+    #[serde(default = "default_protocol")]
+    pub protocol: Protocol,
+    pub items: Vec<ControlTableItem>,
+}
+
+fn default_protocol() -> Protocol {
+    Protocol::One
+}
+
+impl Model {
+    /// Finds an item in the control table by name
+    pub fn item(&self, name: &str) -> Option<&ControlTableItem> {
+        self.items.iter().find(|item| item.name == name)
+    }
+
+    /// The items stored in EEPROM, which keep their values when the servo is
+    /// powered off. On every model the EEPROM area ends where Torque Enable
+    /// begins, so models without a Torque Enable item are stored entirely in
+    /// EEPROM.
+    ///
+    /// ```
+    /// use movement::dynamixel::control_table::builtin_model;
+    ///
+    /// fn main() {
+    ///     let model = builtin_model(12).unwrap();
+    ///     let eeprom = model.eeprom_items();
+    ///     assert_eq!(eeprom.first().unwrap().name, "Model Number");
+    ///     assert_eq!(eeprom.last().unwrap().name, "Shutdown");
+    /// }
+    /// ```
+    pub fn eeprom_items(&self) -> Vec<&ControlTableItem> {
+        let end = self
+            .item("Torque Enable")
+            .map_or(u16::MAX, |item| item.data.address);
+        self.items
+            .iter()
+            .filter(|item| item.data.address < end)
+            .collect()
+    }
+
+    /// Parses and validates a control table described in TOML
+    ///
+    /// ```
+    /// use movement::dynamixel::control_table::{ControlTableError, Model};
+    ///
+    /// fn main() {
+    ///     let model = Model::from_toml(
+    ///         r#"
+    ///         number = 12
+    ///         name = "AX-12A"
+    ///
+    ///         [[items]]
+    ///         name = "CW Angle Limit"
+    ///         category = "Constraint"
+    ///         address = 6
+    ///         size = 2
+    ///         access = "ReadWrite"
+    ///         range = [0, 1023]
+    ///
+    ///         [[items]]
+    ///         name = "CCW Angle Limit"
+    ///         category = "Constraint"
+    ///         address = 7
+    ///         size = 2
+    ///         access = "ReadWrite"
+    ///         "#,
+    ///     );
+    ///
+    ///     assert!(matches!(model, Err(ControlTableError::Overlap { .. })));
+    /// }
+    /// ```
+    pub fn from_toml(source: &str) -> Result<Model, ControlTableError> {
+        let model: Model =
+            toml::from_str(source).map_err(|err| ControlTableError::Parse(err.to_string()))?;
+        model.validate()?;
+
+        Ok(model)
+    }
+
+    /// Reads, parses and validates a control table from a TOML file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Model, ControlTableError> {
+        let source = std::fs::read_to_string(path).map_err(ControlTableError::Io)?;
+        Model::from_toml(&source)
+    }
+
+    /// Checks that every item has a valid size, address & range, and that no
+    /// two items share a name or occupy the same address
+    pub fn validate(&self) -> Result<(), ControlTableError> {
+        for item in self.items.iter() {
+            let size = item.data.size;
+            if ![1, 2, 4].contains(&size) {
+                return Err(ControlTableError::InvalidSize {
+                    name: item.name.clone(),
+                    size,
+                });
+            }
+
+            if item.end() > 1 << 16 {
+                return Err(ControlTableError::InvalidAddress {
+                    name: item.name.clone(),
+                    address: item.data.address,
+                });
+            }
+
+            if let Some((min, max)) = item.data.range {
+                // The range may be stored either signed or unsigned
+                let bits = 8 * size as u32;
+                if min > max || min < -(1 << (bits - 1)) || max > (1 << bits) - 1 {
+                    return Err(ControlTableError::InvalidRange {
+                        name: item.name.clone(),
+                        range: (min, max),
+                    });
+                }
+            }
+        }
+
+        for (i, first) in self.items.iter().enumerate() {
+            for second in self.items[i + 1..].iter() {
+                if first.name == second.name {
+                    return Err(ControlTableError::DuplicateName(first.name.clone()));
+                }
+
+                let (first_start, second_start) =
+                    (first.data.address as u32, second.data.address as u32);
+                if first_start < second.end() && second_start < first.end() {
+                    return Err(ControlTableError::Overlap {
+                        first: first.name.clone(),
+                        second: second.name.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the built-in control table for a model number, as read from address
+/// 0 of the servo.
+///
+/// ```
+/// use movement::dynamixel::control_table::builtin_model;
+///
+/// fn main() {
+///     let model = builtin_model(12).unwrap();
+///     assert_eq!(model.name, "AX-12A");
+///     assert_eq!(model.item("LED").unwrap().data.address, 25);
+/// }
+/// ```
+pub fn builtin_model(number: u16) -> Option<Model> {
+    let (name, protocol, items) = match number {
+        12 => ("AX-12A", Protocol::One, ax_series(70)),
+        18 => ("AX-18A", Protocol::One, ax_series(75)),
+        29 => ("MX-28", Protocol::One, mx_series(false, false)),
+        310 => ("MX-64", Protocol::One, mx_series(true, false)),
+        320 => ("MX-106", Protocol::One, mx_series(true, true)),
+        1020 => ("XM430-W350", Protocol::Two, x_series(true)),
+        1030 => ("XM430-W210", Protocol::Two, x_series(true)),
+        1060 => ("XL430-W250", Protocol::Two, x_series(false)),
+        _ => return None,
+    };
+
+    Some(Model {
+        number,
+        name: name.to_string(),
+        protocol,
+        items,
+    })
+}
+
+/// Gets the control tables of every built-in model
+pub fn builtin_models() -> Vec<Model> {
+    [12, 18, 29, 310, 320, 1020, 1030, 1060]
+        .iter()
+        .filter_map(|number| builtin_model(*number))
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn item(
+    name: &str,
+    category: ControlTableType,
+    address: u16,
+    size: u8,
+    access: AccessLevel,
+    initial_value: Option<&str>,
+    range: Option<(i64, i64)>,
+    units: Option<DataUnit>,
+) -> ControlTableItem {
+    ControlTableItem {
+        name: name.to_string(),
+        category,
+        data: ControlTableData {
+            address,
+            size,
+            description: None,
+            access,
+            initial_value: initial_value.map(String::from),
+            range,
+            units,
+            signed: matches!(range, Some((min, _)) if min < 0),
+        },
+    }
+}
+
+/// Marks the named items as holding two's complement values, for items
+/// which are signed without having a negative range
+fn signed(mut items: Vec<ControlTableItem>, names: &[&str]) -> Vec<ControlTableItem> {
+    for item in items.iter_mut() {
+        if names.contains(&item.name.as_str()) {
+            item.data.signed = true;
+        }
+    }
+
+    items
+}
+
+/// The control table shared by the AX-12A and AX-18A, which only differ in
+/// their default temperature limit
+#[rustfmt::skip]
+fn ax_series(temperature_limit: i64) -> Vec<ControlTableItem> {
+    use AccessLevel::*;
+    use ControlTableType::*;
+
+    let limit = temperature_limit.to_string();
+    vec![
+        item("Model Number", ServoInformation, 0, 2, Read, None, None, None),
+        item("Firmware Version", ServoInformation, 2, 1, Read, None, None, None),
+        item("ID", ServoInformation, 3, 1, ReadWrite, Some("1"), Some((0, 253)), None),
+        item("Baud Rate", ServoInformation, 4, 1, ReadWrite, Some("1"), Some((0, 254)), None),
+        item("Return Delay Time", ServoInformation, 5, 1, ReadWrite, Some("250"), Some((0, 254)), Some(DataUnit::Second)),
+        item("CW Angle Limit", Constraint, 6, 2, ReadWrite, Some("0"), Some((0, 1023)), Some(DataUnit::Pulse)),
+        item("CCW Angle Limit", Constraint, 8, 2, ReadWrite, Some("1023"), Some((0, 1023)), Some(DataUnit::Pulse)),
+        item("Temperature Limit", Constraint, 11, 1, ReadWrite, Some(&limit), Some((0, 99)), Some(DataUnit::DegreesCelcius)),
+        item("Min Voltage Limit", Constraint, 12, 1, ReadWrite, Some("60"), Some((50, 160)), Some(DataUnit::Volts)),
+        item("Max Voltage Limit", Constraint, 13, 1, ReadWrite, Some("140"), Some((50, 160)), Some(DataUnit::Volts)),
+        item("Max Torque", Constraint, 14, 2, ReadWrite, Some("1023"), Some((0, 1023)), Some(DataUnit::Percentage)),
+        item("Status Return Level", ServoInformation, 16, 1, ReadWrite, Some("2"), Some((0, 2)), None),
+        item("Alarm LED", Component, 17, 1, ReadWrite, Some("36"), Some((0, 127)), None),
+        item("Shutdown", Component, 18, 1, ReadWrite, Some("36"), Some((0, 127)), None),
+        item("Torque Enable", Component, 24, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("LED", Component, 25, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("CW Compliance Margin", Component, 26, 1, ReadWrite, Some("1"), Some((0, 255)), Some(DataUnit::Pulse)),
+        item("CCW Compliance Margin", Component, 27, 1, ReadWrite, Some("1"), Some((0, 255)), Some(DataUnit::Pulse)),
+        item("CW Compliance Slope", Component, 28, 1, ReadWrite, Some("32"), Some((0, 254)), None),
+        item("CCW Compliance Slope", Component, 29, 1, ReadWrite, Some("32"), Some((0, 254)), None),
+        item("Goal Position", Motion, 30, 2, ReadWrite, None, Some((0, 1023)), Some(DataUnit::Pulse)),
+        item("Moving Speed", Motion, 32, 2, ReadWrite, None, Some((0, 2047)), Some(DataUnit::RevolutionsPerMinute)),
+        item("Torque Limit", Constraint, 34, 2, ReadWrite, None, Some((0, 1023)), Some(DataUnit::Percentage)),
+        item("Present Position", Sensor, 36, 2, Read, None, None, Some(DataUnit::Pulse)),
+        item("Present Speed", Sensor, 38, 2, Read, None, None, Some(DataUnit::RevolutionsPerMinute)),
+        item("Present Load", Sensor, 40, 2, Read, None, None, Some(DataUnit::Percentage)),
+        item("Present Voltage", Sensor, 42, 1, Read, None, None, Some(DataUnit::Volts)),
+        item("Present Temperature", Sensor, 43, 1, Read, None, None, Some(DataUnit::DegreesCelcius)),
+        item("Registered", ServoInformation, 44, 1, Read, Some("0"), None, None),
+        item("Moving", ServoInformation, 46, 1, Read, Some("0"), None, None),
+        item("Lock", ServoInformation, 47, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("Punch", Constraint, 48, 2, ReadWrite, Some("32"), Some((0, 1023)), None),
+    ]
+}
+
+/// The control table shared by the MX-28, MX-64 & MX-106 on protocol 1. The
+/// MX-64 & MX-106 additionally support current & torque control, and the
+/// MX-106 supports dual-joint drive modes.
+#[rustfmt::skip]
+fn mx_series(torque_control: bool, drive_mode: bool) -> Vec<ControlTableItem> {
+    use AccessLevel::*;
+    use ControlTableType::*;
+
+    let mut items = vec![
+        item("Model Number", ServoInformation, 0, 2, Read, None, None, None),
+        item("Firmware Version", ServoInformation, 2, 1, Read, None, None, None),
+        item("ID", ServoInformation, 3, 1, ReadWrite, Some("1"), Some((0, 253)), None),
+        item("Baud Rate", ServoInformation, 4, 1, ReadWrite, Some("34"), Some((0, 254)), None),
+        item("Return Delay Time", ServoInformation, 5, 1, ReadWrite, Some("250"), Some((0, 254)), Some(DataUnit::Second)),
+        item("CW Angle Limit", Constraint, 6, 2, ReadWrite, Some("0"), Some((0, 4095)), Some(DataUnit::Pulse)),
+        item("CCW Angle Limit", Constraint, 8, 2, ReadWrite, Some("4095"), Some((0, 4095)), Some(DataUnit::Pulse)),
+        item("Temperature Limit", Constraint, 11, 1, ReadWrite, Some("80"), Some((0, 99)), Some(DataUnit::DegreesCelcius)),
+        item("Min Voltage Limit", Constraint, 12, 1, ReadWrite, Some("60"), Some((50, 160)), Some(DataUnit::Volts)),
+        item("Max Voltage Limit", Constraint, 13, 1, ReadWrite, Some("160"), Some((50, 160)), Some(DataUnit::Volts)),
+        item("Max Torque", Constraint, 14, 2, ReadWrite, Some("1023"), Some((0, 1023)), Some(DataUnit::Percentage)),
+        item("Status Return Level", ServoInformation, 16, 1, ReadWrite, Some("2"), Some((0, 2)), None),
+        item("Alarm LED", Component, 17, 1, ReadWrite, Some("36"), Some((0, 127)), None),
+        item("Shutdown", Component, 18, 1, ReadWrite, Some("36"), Some((0, 127)), None),
+        item("Multi Turn Offset", Constraint, 20, 2, ReadWrite, Some("0"), Some((-24576, 24576)), Some(DataUnit::Pulse)),
+        item("Resolution Divider", Constraint, 22, 1, ReadWrite, Some("1"), Some((1, 4)), None),
+        item("Torque Enable", Component, 24, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("LED", Component, 25, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("D Gain", Constraint, 26, 1, ReadWrite, Some("0"), Some((0, 254)), None),
+        item("I Gain", Constraint, 27, 1, ReadWrite, Some("0"), Some((0, 254)), None),
+        item("P Gain", Constraint, 28, 1, ReadWrite, Some("32"), Some((0, 254)), None),
+        item("Goal Position", Motion, 30, 2, ReadWrite, None, Some((0, 4095)), Some(DataUnit::Pulse)),
+        item("Moving Speed", Motion, 32, 2, ReadWrite, None, Some((0, 2047)), Some(DataUnit::RevolutionsPerMinute)),
+        item("Torque Limit", Constraint, 34, 2, ReadWrite, None, Some((0, 1023)), Some(DataUnit::Percentage)),
+        item("Present Position", Sensor, 36, 2, Read, None, None, Some(DataUnit::Pulse)),
+        item("Present Speed", Sensor, 38, 2, Read, None, None, Some(DataUnit::RevolutionsPerMinute)),
+        item("Present Load", Sensor, 40, 2, Read, None, None, Some(DataUnit::Percentage)),
+        item("Present Voltage", Sensor, 42, 1, Read, None, None, Some(DataUnit::Volts)),
+        item("Present Temperature", Sensor, 43, 1, Read, None, None, Some(DataUnit::DegreesCelcius)),
+        item("Registered", ServoInformation, 44, 1, Read, Some("0"), None, None),
+        item("Moving", ServoInformation, 46, 1, Read, Some("0"), None, None),
+        item("Lock", ServoInformation, 47, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("Punch", Constraint, 48, 2, ReadWrite, Some("0"), Some((0, 1023)), None),
+        item("Realtime Tick", ServoInformation, 50, 2, Read, None, None, None),
+        item("Goal Acceleration", Motion, 73, 1, ReadWrite, Some("0"), Some((0, 254)), None),
+    ];
+
+    if torque_control {
+        items.extend(vec![
+            item("Current", Sensor, 68, 2, ReadWrite, Some("0"), Some((0, 4095)), Some(DataUnit::Amps)),
+            item("Torque Control Mode Enable", ServoInformation, 70, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+            item("Goal Torque", Motion, 71, 2, ReadWrite, Some("0"), Some((0, 2047)), Some(DataUnit::Percentage)),
+        ]);
+    }
+
+    if drive_mode {
+        items.push(item("Drive Mode", ServoInformation, 10, 1, ReadWrite, Some("0"), Some((0, 3)), None));
+    }
+
+    items
+}
+
+/// The control table shared by the X series on protocol 2. The XM430 is
+/// current controlled, while the XL430 only reports its load.
+#[rustfmt::skip]
+fn x_series(current_control: bool) -> Vec<ControlTableItem> {
+    use AccessLevel::*;
+    use ControlTableType::*;
+
+    let (voltage_range, temperature, velocity) = if current_control {
+        ((95, 160), "80", "200")
+    } else {
+        ((60, 140), "72", "265")
+    };
+    let (min_voltage, max_voltage) = (voltage_range.0.to_string(), voltage_range.1.to_string());
+
+    let mut items = vec![
+        item("Model Number", ServoInformation, 0, 2, Read, None, None, None),
+        item("Model Information", ServoInformation, 2, 4, Read, None, None, None),
+        item("Firmware Version", ServoInformation, 6, 1, Read, None, None, None),
+        item("ID", ServoInformation, 7, 1, ReadWrite, Some("1"), Some((0, 252)), None),
+        item("Baud Rate", ServoInformation, 8, 1, ReadWrite, Some("1"), Some((0, 7)), None),
+        item("Return Delay Time", ServoInformation, 9, 1, ReadWrite, Some("250"), Some((0, 254)), Some(DataUnit::Second)),
+        item("Drive Mode", ServoInformation, 10, 1, ReadWrite, Some("0"), Some((0, 13)), None),
+        item("Operating Mode", ServoInformation, 11, 1, ReadWrite, Some("3"), Some((0, 16)), None),
+        item("Secondary ID", ServoInformation, 12, 1, ReadWrite, Some("255"), Some((0, 255)), None),
+        item("Protocol Type", ServoInformation, 13, 1, ReadWrite, Some("2"), Some((1, 2)), None),
+        item("Homing Offset", Constraint, 20, 4, ReadWrite, Some("0"), Some((-1_044_479, 1_044_479)), Some(DataUnit::Pulse)),
+        item("Moving Threshold", Constraint, 24, 4, ReadWrite, Some("10"), Some((0, 1023)), Some(DataUnit::RevolutionsPerMinute)),
+        item("Temperature Limit", Constraint, 31, 1, ReadWrite, Some(temperature), Some((0, 100)), Some(DataUnit::DegreesCelcius)),
+        item("Max Voltage Limit", Constraint, 32, 2, ReadWrite, Some(&max_voltage), Some(voltage_range), Some(DataUnit::Volts)),
+        item("Min Voltage Limit", Constraint, 34, 2, ReadWrite, Some(&min_voltage), Some(voltage_range), Some(DataUnit::Volts)),
+        item("PWM Limit", Constraint, 36, 2, ReadWrite, Some("885"), Some((0, 885)), Some(DataUnit::Percentage)),
+        item("Velocity Limit", Constraint, 44, 4, ReadWrite, Some(velocity), Some((0, 1023)), Some(DataUnit::RevolutionsPerMinute)),
+        item("Max Position Limit", Constraint, 48, 4, ReadWrite, Some("4095"), Some((0, 4095)), Some(DataUnit::Pulse)),
+        item("Min Position Limit", Constraint, 52, 4, ReadWrite, Some("0"), Some((0, 4095)), Some(DataUnit::Pulse)),
+        item("Shutdown", Component, 63, 1, ReadWrite, Some("52"), Some((0, 255)), None),
+        item("Torque Enable", Component, 64, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("LED", Component, 65, 1, ReadWrite, Some("0"), Some((0, 1)), None),
+        item("Status Return Level", ServoInformation, 68, 1, ReadWrite, Some("2"), Some((0, 2)), None),
+        item("Registered Instruction", ServoInformation, 69, 1, Read, Some("0"), None, None),
+        item("Hardware Error Status", ServoInformation, 70, 1, Read, Some("0"), None, None),
+        item("Velocity I Gain", Constraint, 76, 2, ReadWrite, None, Some((0, 16383)), None),
+        item("Velocity P Gain", Constraint, 78, 2, ReadWrite, None, Some((0, 16383)), None),
+        item("Position D Gain", Constraint, 80, 2, ReadWrite, Some("0"), Some((0, 16383)), None),
+        item("Position I Gain", Constraint, 82, 2, ReadWrite, Some("0"), Some((0, 16383)), None),
+        item("Position P Gain", Constraint, 84, 2, ReadWrite, None, Some((0, 16383)), None),
+        item("Feedforward 2nd Gain", Constraint, 88, 2, ReadWrite, Some("0"), Some((0, 16383)), None),
+        item("Feedforward 1st Gain", Constraint, 90, 2, ReadWrite, Some("0"), Some((0, 16383)), None),
+        item("Bus Watchdog", ServoInformation, 98, 1, ReadWrite, Some("0"), Some((0, 127)), None),
+        item("Goal PWM", Motion, 100, 2, ReadWrite, None, Some((-885, 885)), Some(DataUnit::Percentage)),
+        item("Goal Velocity", Motion, 104, 4, ReadWrite, None, Some((-1023, 1023)), Some(DataUnit::RevolutionsPerMinute)),
+        item("Profile Acceleration", Motion, 108, 4, ReadWrite, Some("0"), Some((0, 32767)), None),
+        item("Profile Velocity", Motion, 112, 4, ReadWrite, Some("0"), Some((0, 32767)), Some(DataUnit::RevolutionsPerMinute)),
+        item("Goal Position", Motion, 116, 4, ReadWrite, None, Some((0, 4095)), Some(DataUnit::Pulse)),
+        item("Realtime Tick", ServoInformation, 120, 2, Read, None, None, None),
+        item("Moving", ServoInformation, 122, 1, Read, Some("0"), None, None),
+        item("Moving Status", ServoInformation, 123, 1, Read, Some("0"), None, None),
+        item("Present PWM", Sensor, 124, 2, Read, None, None, Some(DataUnit::Percentage)),
+        item("Present Velocity", Sensor, 128, 4, Read, None, None, Some(DataUnit::RevolutionsPerMinute)),
+        item("Present Position", Sensor, 132, 4, Read, None, None, Some(DataUnit::Pulse)),
+        item("Velocity Trajectory", Sensor, 136, 4, Read, None, None, Some(DataUnit::RevolutionsPerMinute)),
+        item("Position Trajectory", Sensor, 140, 4, Read, None, None, Some(DataUnit::Pulse)),
+        item("Present Input Voltage", Sensor, 144, 2, Read, None, None, Some(DataUnit::Volts)),
+        item("Present Temperature", Sensor, 146, 1, Read, None, None, Some(DataUnit::DegreesCelcius)),
+    ];
+
+    if current_control {
+        items.extend(vec![
+            item("Current Limit", Constraint, 38, 2, ReadWrite, Some("1193"), Some((0, 1193)), Some(DataUnit::Amps)),
+            item("Goal Current", Motion, 102, 2, ReadWrite, None, Some((-1193, 1193)), Some(DataUnit::Amps)),
+            item("Present Current", Sensor, 126, 2, Read, None, None, Some(DataUnit::Amps)),
+        ]);
+    } else {
+        items.push(item("Present Load", Sensor, 126, 2, Read, None, None, Some(DataUnit::Percentage)));
+    }
+
+    // Sensor items report negative values when turning or pushing clockwise
+    signed(items, &[
+        "Present PWM",
+        "Present Current",
+        "Present Load",
+        "Present Velocity",
+        "Present Position",
+        "Velocity Trajectory",
+        "Position Trajectory",
+    ])
+}
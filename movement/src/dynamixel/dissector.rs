@@ -0,0 +1,776 @@
+//! # Packet dissector
+//! Splits a stream of raw bytes into the packets of either protocol and
+//! annotates them for debugging, in the manner of Wireshark. The instruction
+//! & ID of each packet are decoded, as are the errors reported by status
+//! packets, and the data written to or read from a servo is shown as named
+//! items of its model's control table. Packets with an invalid checksum (or
+//! CRC) are flagged rather than discarded, and any bytes which do not form a
+//! packet are shown as they are.
+//!
+//! Status packets do not say which items they contain, so the dissector
+//! remembers the last instruction sent to each servo (including each servo
+//! addressed by a sync or bulk read) to annotate its response. The combined
+//! response to a protocol 2 fast sync or fast bulk read is split into the
+//! data of each servo.
+//!
+//! The error byte of a protocol 1 status packet sits where an instruction
+//! packet has its instruction, so the two can only be told apart reliably
+//! when the direction of each packet is known, as it is in a capture (see
+//! [`Dissector::dissect_captured`]). In a raw log of the bus, classification
+//! is a heuristic: a packet from a servo which has been sent an instruction
+//! is taken as its response if it has the length of one, unless it is an
+//! echo of the instruction itself. Any other packet naming an instruction is
+//! taken as a new instruction, and the unanswered one is forgotten.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::dissector::Dissector;
+//!
+//! fn main() {
+//!     let mut dissector = Dissector::new(builtin_model(12));
+//!
+//!     // A write of 512 to the Goal Position, answered with an angle limit error
+//!     let segments = dissector.dissect(&[
+//!         0xFF, 0xFF, 0x01, 0x05, 0x03, 0x1E, 0x00, 0x02, 0xD6,
+//!         0xFF, 0xFF, 0x01, 0x02, 0x02, 0xFA,
+//!     ]);
+//!     assert_eq!(
+//!         segments[0].to_string(),
+//!         "P1 ID 1 Write: FF FF 01 05 03 1E 00 02 D6\n    Goal Position (30) = 512"
+//!     );
+//!     assert_eq!(segments[1].to_string(), "P1 ID 1 Status AngleLimit: FF FF 01 02 02 FA");
+//!
+//!     // A read of the Present Position, with a corrupted response
+//!     dissector.dissect(&[0xFF, 0xFF, 0x01, 0x04, 0x02, 0x24, 0x02, 0xD2]);
+//!     let segments = dissector.dissect(&[0xFF, 0xFF, 0x01, 0x04, 0x00, 0x00, 0x02, 0x00]);
+//!     assert_eq!(
+//!         segments[0].to_string(),
+//!         "P1 ID 1 Status OK: FF FF 01 04 00 00 02 00\n    \
+//!          Present Position (36) = 512\n    \
+//!          !! Invalid checksum: expected F8, found 00"
+//!     );
+//! }
+//! ```
+
+use super::capture::{CapturedPacket, Direction};
+use super::control_table::Model;
+use super::{protocol_one, protocol_two, DataBytes, Packet, Protocol};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The header at the start of every protocol 1 packet
+const HEADER_ONE: [u8; 2] = [0xFF, 0xFF];
+
+/// The header (and reserved byte) at the start of every protocol 2 packet
+const HEADER_TWO: [u8; 4] = [0xFF, 0xFF, 0xFD, 0x00];
+
+/// The opcode used by every protocol 2 status packet
+const STATUS_OPCODE: u8 = 0x55;
+
+/// Writes bytes as space separated pairs of hex digits
+///
+/// ```
+/// use movement::dynamixel::dissector::to_hex;
+///
+/// fn main() {
+///     assert_eq!(to_hex(&[0xFF, 0x01, 0x2A]), "FF 01 2A");
+/// }
+/// ```
+pub fn to_hex(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    hex.join(" ")
+}
+
+/// Whether a packet is an instruction, or the status returned by a servo
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    /// The name of the instruction
+    Instruction(String),
+    /// The errors reported by the servo, or OK
+    Status(String),
+}
+
+/// A single annotated value of a packet
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub value: String,
+}
+
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} = {}", self.name, self.value)
+    }
+}
+
+/// A decoded packet of either protocol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub protocol: Protocol,
+    pub id: u8,
+    pub kind: FrameKind,
+    pub fields: Vec<Field>,
+    /// The expected & received checksum (or CRC), if they differ
+    pub invalid_checksum: Option<(u16, u16)>,
+    pub bytes: Vec<u8>,
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let protocol = match self.protocol {
+            Protocol::One => "P1",
+            Protocol::Two => "P2",
+        };
+        let kind = match self.kind {
+            FrameKind::Instruction(ref instruction) => instruction.clone(),
+            FrameKind::Status(ref status) => format!("Status {}", status),
+        };
+        write!(
+            f,
+            "{} ID {} {}: {}",
+            protocol,
+            self.id,
+            kind,
+            to_hex(&self.bytes)
+        )?;
+
+        for field in self.fields.iter() {
+            write!(f, "\n    {}", field)?;
+        }
+
+        if let Some((expected, found)) = self.invalid_checksum {
+            let width = match self.protocol {
+                Protocol::One => 2,
+                Protocol::Two => 4,
+            };
+            write!(
+                f,
+                "\n    !! Invalid checksum: expected {:02$X}, found {:02$X}",
+                expected, found, width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A part of a stream of bytes, which either forms a packet or does not
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Frame(Frame),
+    Unframed(Vec<u8>),
+}
+
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Segment::Frame(frame) => write!(f, "{}", frame),
+            Segment::Unframed(bytes) => write!(f, "?? Unframed: {}", to_hex(bytes)),
+        }
+    }
+}
+
+/// What a status packet is expected to contain, given the instruction that
+/// it responds to
+#[derive(Clone, Debug)]
+enum Request {
+    Read {
+        address: u16,
+        length: u16,
+    },
+    /// A fast sync or fast bulk read of the ID, address & length of each
+    /// servo, which all respond in a single status packet
+    FastRead(Vec<(u8, u16, u16)>),
+    Ping,
+    Other,
+}
+
+/// An instruction awaiting a status packet
+#[derive(Clone, Debug)]
+struct Pending {
+    bytes: Vec<u8>,
+    request: Request,
+}
+
+/// Decodes packets, naming the items they address using the control table
+/// of each servo's model
+#[derive(Clone, Debug, Default)]
+pub struct Dissector {
+    model: Option<Model>,
+    models: HashMap<u8, Model>,
+    pending: HashMap<u8, Pending>,
+}
+
+impl Dissector {
+    /// Creates a dissector which assumes every servo is of the given model.
+    /// Without a model, data is shown by address.
+    pub fn new(model: Option<Model>) -> Dissector {
+        Dissector {
+            model,
+            ..Dissector::default()
+        }
+    }
+
+    /// Sets the model of a single servo
+    pub fn set_model(&mut self, id: u8, model: Model) {
+        self.models.insert(id, model);
+    }
+
+    /// Splits a stream of bytes into packets, decoding each in turn
+    pub fn dissect(&mut self, bytes: &[u8]) -> Vec<Segment> {
+        self.split(bytes, false, None).0
+    }
+
+    /// Decodes the packets of a capture, using the direction each packet
+    /// was sent in to tell instructions & status packets apart
+    ///
+    /// ```
+    /// use movement::dynamixel::capture::{CapturedPacket, Direction};
+    /// use movement::dynamixel::dissector::{Dissector, FrameKind, Segment};
+    ///
+    /// fn main() {
+    ///     let mut dissector = Dissector::new(None);
+    ///
+    ///     // A status packet reporting overheating, which looks like a RegWrite
+    ///     let received = CapturedPacket {
+    ///         time: 0,
+    ///         direction: Direction::Received,
+    ///         bytes: vec![0xFF, 0xFF, 0x01, 0x02, 0x04, 0xF8],
+    ///     };
+    ///     match &dissector.dissect_captured(&received)[0] {
+    ///         Segment::Frame(frame) => {
+    ///             assert_eq!(frame.kind, FrameKind::Status("Overheating".to_string()))
+    ///         }
+    ///         other => panic!("expected a frame, found {:?}", other),
+    ///     }
+    /// }
+    /// ```
+    pub fn dissect_captured(&mut self, packet: &CapturedPacket) -> Vec<Segment> {
+        self.split(&packet.bytes, false, Some(packet.direction)).0
+    }
+
+    /// Splits the bytes received so far into packets, stopping at a packet
+    /// which has not yet been received in full. The number of bytes decoded
+    /// is returned with the packets, so that the rest can be decoded once
+    /// more bytes have been received.
+    ///
+    /// ```
+    /// use movement::dynamixel::dissector::Dissector;
+    ///
+    /// fn main() {
+    ///     let mut dissector = Dissector::new(None);
+    ///     let (segments, used) = dissector.dissect_partial(&[
+    ///         0xFF, 0xFF, 0x01, 0x02, 0x01, 0xFB, 0xFF, 0xFF, 0x01, 0x02,
+    ///     ]);
+    ///
+    ///     assert_eq!(segments.len(), 1);
+    ///     assert_eq!(used, 6);
+    /// }
+    /// ```
+    pub fn dissect_partial(&mut self, bytes: &[u8]) -> (Vec<Segment>, usize) {
+        self.split(bytes, true, None)
+    }
+
+    /// Splits bytes into packets, either stopping at or skipping over the
+    /// start of a packet which is incomplete
+    fn split(
+        &mut self,
+        bytes: &[u8],
+        partial: bool,
+        direction: Option<Direction>,
+    ) -> (Vec<Segment>, usize) {
+        let mut segments = vec![];
+        let mut unframed = vec![];
+        let mut start = 0;
+
+        while start < bytes.len() {
+            match framing(&bytes[start..]) {
+                Framing::Complete(protocol, length) => {
+                    if !unframed.is_empty() {
+                        segments.push(Segment::Unframed(std::mem::take(&mut unframed)));
+                    }
+
+                    let frame = &bytes[start..start + length];
+                    segments.push(Segment::Frame(match protocol {
+                        Protocol::One => self.frame_one(frame, direction),
+                        Protocol::Two => self.frame_two(frame),
+                    }));
+                    start += length;
+                }
+                Framing::Incomplete if partial => break,
+                Framing::Incomplete | Framing::Invalid => {
+                    unframed.push(bytes[start]);
+                    start += 1;
+                }
+            }
+        }
+
+        if !unframed.is_empty() {
+            segments.push(Segment::Unframed(unframed));
+        }
+
+        (segments, start)
+    }
+
+    /// Decodes a single packet, such as one of the `sent_packets` of a servo
+    pub fn packet(&mut self, packet: &Packet) -> Frame {
+        match packet {
+            Packet::ProtocolOne(packet) => {
+                let direction = match packet.packet_type {
+                    protocol_one::PacketType::Instruction(_) => Direction::Sent,
+                    protocol_one::PacketType::Status(_) => Direction::Received,
+                };
+                self.frame_one(&packet.to_bytes(), Some(direction))
+            }
+            Packet::ProtocolTwo(packet) => self.frame_two(&packet.to_bytes()),
+        }
+    }
+
+    /// Decodes a complete protocol 1 packet, which is classified by the
+    /// direction it was sent in if that is known
+    fn frame_one(&mut self, bytes: &[u8], direction: Option<Direction>) -> Frame {
+        let id = bytes[2];
+        let opcode = bytes[4];
+        let params = &bytes[5..bytes.len() - 1];
+
+        let checksum = bytes[bytes.len() - 1];
+        let expected = !bytes[2..bytes.len() - 1]
+            .iter()
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        let invalid_checksum = if checksum == expected {
+            None
+        } else {
+            Some((expected as u16, checksum as u16))
+        };
+
+        let named = protocol_one::InstructionType::try_from(opcode)
+            .map(|instruction| format!("{:?}", instruction));
+        let instruction = match direction {
+            Some(Direction::Sent) => {
+                Some(named.unwrap_or_else(|_| format!("Unknown instruction {:02X}", opcode)))
+            }
+            Some(Direction::Received) => None,
+            None => named.ok().filter(|_| !self.is_response(id, bytes, params)),
+        };
+
+        let (kind, fields) = match instruction {
+            Some(name) => {
+                let fields = self.instruction(Protocol::One, id, &name, params, bytes);
+                (FrameKind::Instruction(name), fields)
+            }
+            None => {
+                let errors = protocol_one::StatusType::get_error_types(&opcode);
+                let status = if errors.is_empty() {
+                    "OK".to_string()
+                } else {
+                    let errors: Vec<String> =
+                        errors.iter().map(|error| format!("{:?}", error)).collect();
+                    errors.join(", ")
+                };
+                (FrameKind::Status(status), self.status(id, params))
+            }
+        };
+
+        Frame {
+            protocol: Protocol::One,
+            id,
+            kind,
+            fields,
+            invalid_checksum,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// Whether a protocol 1 packet naming an instruction is more likely to
+    /// be the response to the instruction awaiting a status packet from the
+    /// servo, judged by its length
+    fn is_response(&self, id: u8, bytes: &[u8], params: &[u8]) -> bool {
+        match self.pending.get(&id) {
+            Some(pending) if pending.bytes != bytes => match pending.request {
+                Request::Read { length, .. } => params.len() == length as usize,
+                Request::FastRead(_) | Request::Ping | Request::Other => params.is_empty(),
+            },
+            _ => false,
+        }
+    }
+
+    /// Decodes a complete protocol 2 packet
+    fn frame_two(&mut self, bytes: &[u8]) -> Frame {
+        let id = bytes[4];
+        let data = protocol_two::unstuff(&bytes[7..bytes.len() - 2]);
+
+        let checksum = LittleEndian::read_u16(&bytes[bytes.len() - 2..]);
+        let expected = protocol_two::crc(&bytes[..bytes.len() - 2]);
+        let invalid_checksum = if checksum == expected {
+            None
+        } else {
+            Some((expected, checksum))
+        };
+
+        let (kind, fields) = match data[0] {
+            STATUS_OPCODE if data.len() > 1 => (
+                FrameKind::Status(status_two(data[1])),
+                self.status(id, &data[2..]),
+            ),
+            opcode => {
+                let name = match protocol_two::InstructionType::try_from(opcode) {
+                    Ok(instruction) => format!("{:?}", instruction),
+                    Err(_) => format!("Unknown instruction {:02X}", opcode),
+                };
+                let fields = self.instruction(Protocol::Two, id, &name, &data[1..], bytes);
+                (FrameKind::Instruction(name), fields)
+            }
+        };
+
+        Frame {
+            protocol: Protocol::Two,
+            id,
+            kind,
+            fields,
+            invalid_checksum,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// Annotates the parameters of an instruction, and remembers it to
+    /// annotate the servo's response
+    fn instruction(
+        &mut self,
+        protocol: Protocol,
+        id: u8,
+        name: &str,
+        params: &[u8],
+        bytes: &[u8],
+    ) -> Vec<Field> {
+        // Addresses & lengths are a single byte in protocol 1, and two bytes
+        // in protocol 2
+        let width = match protocol {
+            Protocol::One => 1,
+            Protocol::Two => 2,
+        };
+        let word = |bytes: &[u8]| LittleEndian::read_uint(bytes, width) as u16;
+
+        let (request, fields) = match name {
+            "Ping" => (Request::Ping, vec![]),
+            "Read" if params.len() == 2 * width => {
+                let (address, length) = (word(&params[..width]), word(&params[width..]));
+                let fields = vec![
+                    Field {
+                        name: "Address".to_string(),
+                        value: self.describe_address(id, address),
+                    },
+                    Field {
+                        name: "Length".to_string(),
+                        value: length.to_string(),
+                    },
+                ];
+                (Request::Read { address, length }, fields)
+            }
+            "Write" | "RegWrite" if params.len() > width => {
+                let address = word(&params[..width]);
+                (Request::Other, self.items(id, address, &params[width..]))
+            }
+            "SyncWrite" if params.len() > 2 * width => {
+                let (address, length) = (word(&params[..width]), word(&params[width..]));
+                let mut fields = vec![];
+                for chunk in params[2 * width..].chunks(length as usize + 1) {
+                    for field in self.items(chunk[0], address, &chunk[1..]) {
+                        fields.push(Field {
+                            name: format!("ID {}: {}", chunk[0], field.name),
+                            value: field.value,
+                        });
+                    }
+                }
+                (Request::Other, fields)
+            }
+            "BulkRead" if protocol == Protocol::One && params.len() % 3 == 1 => {
+                let reads: Vec<(u8, u16, u16)> = params[1..]
+                    .chunks(3)
+                    .map(|chunk| (chunk[1], chunk[2] as u16, chunk[0] as u16))
+                    .collect();
+                (Request::Other, self.reads(&reads, bytes, false))
+            }
+            "SyncRead" | "FastSyncRead" if protocol == Protocol::Two && params.len() > 4 => {
+                let (address, length) = (word(&params[..2]), word(&params[2..4]));
+                let reads: Vec<(u8, u16, u16)> = params[4..]
+                    .iter()
+                    .map(|servo| (*servo, address, length))
+                    .collect();
+                (
+                    Request::Other,
+                    self.reads(&reads, bytes, name == "FastSyncRead"),
+                )
+            }
+            "BulkRead" | "FastBulkRead"
+                if protocol == Protocol::Two
+                    && !params.is_empty()
+                    && params.len().is_multiple_of(5) =>
+            {
+                let reads: Vec<(u8, u16, u16)> = params
+                    .chunks(5)
+                    .map(|chunk| (chunk[0], word(&chunk[1..3]), word(&chunk[3..5])))
+                    .collect();
+                (
+                    Request::Other,
+                    self.reads(&reads, bytes, name == "FastBulkRead"),
+                )
+            }
+            _ => (Request::Other, raw_parameters(params)),
+        };
+
+        // Broadcast instructions are never responded to
+        if id != 0xFE {
+            self.pending.insert(
+                id,
+                Pending {
+                    bytes: bytes.to_vec(),
+                    request,
+                },
+            );
+        }
+
+        fields
+    }
+
+    /// Describes the ID, address & length of each servo read by a sync or
+    /// bulk read, and remembers the read to annotate the responses. Each
+    /// servo responds to its own part of the read, unless the read is fast,
+    /// when every servo responds in a single status packet from the
+    /// broadcast ID.
+    fn reads(&mut self, reads: &[(u8, u16, u16)], bytes: &[u8], fast: bool) -> Vec<Field> {
+        let mut fields = vec![];
+        for &(servo, address, length) in reads {
+            fields.push(Field {
+                name: format!("ID {}", servo),
+                value: format!(
+                    "{} bytes from {}",
+                    length,
+                    self.describe_address(servo, address)
+                ),
+            });
+
+            if !fast {
+                self.pending.insert(
+                    servo,
+                    Pending {
+                        bytes: bytes.to_vec(),
+                        request: Request::Read { address, length },
+                    },
+                );
+            }
+        }
+
+        if fast {
+            self.pending.insert(
+                0xFE,
+                Pending {
+                    bytes: bytes.to_vec(),
+                    request: Request::FastRead(reads.to_vec()),
+                },
+            );
+        }
+
+        fields
+    }
+
+    /// Names the items read from each servo in the combined response to a
+    /// fast read. The data of each servo follows its ID, and is followed by
+    /// a CRC & the error of the next servo, except for the last servo, whose
+    /// CRC is that of the whole packet. `None` is returned if the response
+    /// does not match the read.
+    fn fast_read(&self, reads: &[(u8, u16, u16)], params: &[u8]) -> Option<Vec<Field>> {
+        let mut fields = vec![];
+        let mut rest = params;
+        for (index, &(servo, address, length)) in reads.iter().enumerate() {
+            if index > 0 {
+                let error = *rest.get(2)?;
+                rest = &rest[3..];
+                if error != 0 {
+                    fields.push(Field {
+                        name: format!("ID {}", servo),
+                        value: status_two(error),
+                    });
+                }
+            }
+
+            let (&id, data) = rest.split_first()?;
+            if id != servo || data.len() < length as usize {
+                return None;
+            }
+            for field in self.items(servo, address, &data[..length as usize]) {
+                fields.push(Field {
+                    name: format!("ID {}: {}", servo, field.name),
+                    value: field.value,
+                });
+            }
+            rest = &data[length as usize..];
+        }
+
+        if rest.is_empty() {
+            Some(fields)
+        } else {
+            None
+        }
+    }
+
+    /// Annotates the parameters of a status packet using the instruction it
+    /// responds to
+    fn status(&mut self, id: u8, params: &[u8]) -> Vec<Field> {
+        let request = self.pending.remove(&id).map(|pending| pending.request);
+        match request {
+            Some(Request::Read { address, length }) if params.len() == length as usize => {
+                self.items(id, address, params)
+            }
+            Some(Request::FastRead(reads)) => self
+                .fast_read(&reads, params)
+                .unwrap_or_else(|| raw_parameters(params)),
+            Some(Request::Ping) if params.len() == 3 => vec![
+                Field {
+                    name: "Model Number".to_string(),
+                    value: LittleEndian::read_u16(params).to_string(),
+                },
+                Field {
+                    name: "Firmware Version".to_string(),
+                    value: params[2].to_string(),
+                },
+            ],
+            _ => raw_parameters(params),
+        }
+    }
+
+    /// The model of a servo, if it is known
+    fn model(&self, id: u8) -> Option<&Model> {
+        self.models.get(&id).or(self.model.as_ref())
+    }
+
+    /// Names each item of the servo's control table held in data starting at
+    /// the given address. Bytes which are not the start of an item, or which
+    /// hold only part of one, are shown by address, and any bytes past the
+    /// last address are shown together.
+    fn items(&self, id: u8, address: u16, data: &[u8]) -> Vec<Field> {
+        let model = self.model(id);
+        let mut fields = vec![];
+        let mut offset = 0;
+
+        while offset < data.len() {
+            let start = match u16::try_from(address as usize + offset) {
+                Ok(start) => start,
+                Err(_) => {
+                    fields.push(Field {
+                        name: format!("Past address {}", u16::MAX),
+                        value: to_hex(&data[offset..]),
+                    });
+                    break;
+                }
+            };
+            let item = model
+                .and_then(|model| model.items.iter().find(|item| item.data.address == start))
+                .filter(|item| offset + item.data.size as usize <= data.len());
+
+            let size = item.map_or(1, |item| item.data.size as usize);
+            let bytes = &data[offset..offset + size];
+            fields.push(match item {
+                Some(item) => Field {
+                    name: format!("{} ({})", item.name, start),
+                    value: DataBytes::decode(bytes, item.data.signed)
+                        .map_or_else(|| to_hex(bytes), |value| i64::from(value).to_string()),
+                },
+                None => Field {
+                    name: format!("Address {}", start),
+                    value: to_hex(bytes),
+                },
+            });
+            offset += size;
+        }
+
+        fields
+    }
+
+    /// Describes an address by the item of the servo's control table at it
+    fn describe_address(&self, id: u8, address: u16) -> String {
+        let model = self.model(id);
+        match model.and_then(|model| model.items.iter().find(|item| item.data.address == address)) {
+            Some(item) => format!("{} ({})", address, item.name),
+            None => address.to_string(),
+        }
+    }
+}
+
+/// Describes the error byte of a protocol 2 status packet
+fn status_two(error: u8) -> String {
+    let mut status = match protocol_two::StatusType::try_from(error) {
+        Ok(protocol_two::StatusType::Success) => "OK".to_string(),
+        Ok(error) => format!("{:?}", error),
+        Err(_) => format!("Unknown error {:02X}", error & 0x7F),
+    };
+    if error & 0x80 != 0 {
+        status.push_str(" (alert)");
+    }
+
+    status
+}
+
+/// Shows the parameters of a packet which cannot be annotated
+fn raw_parameters(params: &[u8]) -> Vec<Field> {
+    if params.is_empty() {
+        return vec![];
+    }
+
+    vec![Field {
+        name: "Parameters".to_string(),
+        value: to_hex(params),
+    }]
+}
+
+/// Whether a packet starts at the start of some data
+enum Framing {
+    /// A complete packet of the protocol & length
+    Complete(Protocol, usize),
+    /// The start of a packet which has not been received in full
+    Incomplete,
+    Invalid,
+}
+
+/// Finds the protocol & length of the packet at the start of the data
+fn framing(data: &[u8]) -> Framing {
+    if data.len() < HEADER_TWO.len() && HEADER_TWO.starts_with(data) {
+        return Framing::Incomplete;
+    }
+
+    if data.starts_with(&HEADER_TWO) {
+        if data.len() < 7 {
+            return Framing::Incomplete;
+        }
+
+        // A packet has at least an instruction & CRC
+        let length = LittleEndian::read_u16(&data[5..7]) as usize;
+        let total = length + 7;
+        if length >= 3 {
+            return if data.len() >= total {
+                Framing::Complete(Protocol::Two, total)
+            } else {
+                Framing::Incomplete
+            };
+        }
+    }
+
+    if data.starts_with(&HEADER_ONE) {
+        if data.len() < 4 {
+            return Framing::Incomplete;
+        }
+
+        // 0xFF is not a valid ID, and a packet has at least an error & checksum
+        let (id, length) = (data[2], data[3] as usize);
+        let total = length + 4;
+        if id != 0xFF && length >= 2 {
+            return if data.len() >= total {
+                Framing::Complete(Protocol::One, total)
+            } else {
+                Framing::Incomplete
+            };
+        }
+    }
+
+    Framing::Invalid
+}
@@ -1,30 +1,259 @@
+pub mod actuator;
+pub mod backup;
+pub mod bus;
+pub mod capture;
+pub mod commission;
+pub mod component;
+pub mod control_table;
+pub mod dissector;
+pub mod motion;
 pub mod protocol_one;
+pub mod protocol_two;
+pub mod reliability;
+pub mod scan;
 pub mod servo_connection;
+pub mod simulator;
+pub mod sniffer;
+pub mod telemetry;
+pub mod units;
 
 use std::collections::HashMap;
 
 use sensor::DataSensor;
 use std::io::{Read, Write};
 
-use byteorder::{LittleEndian, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use serde::{Deserialize, Serialize};
 
-// Extend this with protocol 2 packet when implemented
 /// A protocol-agnostic representation of a Dynamixel packet
+#[derive(Clone, Debug)]
 pub enum Packet {
     ProtocolOne(protocol_one::Packet),
+    ProtocolTwo(protocol_two::Packet),
+}
+
+impl From<protocol_one::Packet> for Packet {
+    fn from(packet: protocol_one::Packet) -> Packet {
+        Packet::ProtocolOne(packet)
+    }
+}
+
+impl From<protocol_two::Packet> for Packet {
+    fn from(packet: protocol_two::Packet) -> Packet {
+        Packet::ProtocolTwo(packet)
+    }
+}
+
+/// The versions of the Dynamixel communication protocol
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Protocol {
+    One,
+    Two,
+}
+
+/// The ways in which communicating with a Dynamixel can fail. Every method
+/// which talks to a servo returns this error rather than panicking, so that
+/// a single corrupted or dropped packet can be retried or ignored by the
+/// caller.
+#[derive(Debug)]
+pub enum DynamixelError {
+    /// The underlying connection failed
+    Io(std::io::Error),
+    /// The servo did not respond in time
+    Timeout,
+    /// A packet could not be crafted from the given data
+    InvalidPacket(String),
+    /// The servo responded with a malformed packet
+    Packet(protocol_one::PacketReadError),
+    /// A protocol 1 servo reported one or more errors in its status packet
+    Status(Vec<protocol_one::StatusType>),
+    /// A protocol 2 servo reported an error in its status packet
+    ProtocolTwoStatus {
+        error: protocol_two::StatusType,
+        alert: bool,
+    },
+    /// A response was received from a different servo than was addressed
+    IDMismatch { expected: u8, found: u8 },
+    /// The servo reported a model number without a known control table
+    UnknownModel(u16),
+    /// The control table has no item with the given name
+    UnknownItem(String),
+    /// A response was expected from an instruction sent to all servos, which
+    /// never respond to broadcasts
+    Broadcast,
+    /// A coordinated motion was cancelled as the listed servos failed to
+    /// stage their moves
+    Motion(Vec<(u8, DynamixelError)>),
+    /// The operation cannot be performed while the servo is in its current
+    /// wheel or joint mode
+    WrongMode {
+        mode: DynamixelMode,
+        operation: &'static str,
+    },
+    /// A physical quantity was given in different units than the item is
+    /// measured in, or the item has no physical units
+    UnitMismatch {
+        name: String,
+        expected: Option<sensor::DataUnit>,
+        found: sensor::DataUnit,
+    },
+    /// The value is outside of the range of the item
+    OutOfRange { name: String, value: i64 },
+    /// The value needs more bytes than the item holds
+    TooLarge { name: String, size: u8, value: u64 },
+    /// The item cannot be written to
+    ReadOnly(String),
+    /// Another servo already responds to the ID
+    IDInUse(u8),
+    /// The servo did not respond at its new ID & baud rate after they were
+    /// changed
+    Unverified { id: u8, baud_rate: Option<u32> },
+    /// A single servo was expected on the bus, but the given number were found
+    ServoCount(usize),
+    /// A backup of one model was restored to a servo of another model
+    ModelMismatch { expected: u16, found: u16 },
+    /// The operation is not supported by servos using the protocol
+    UnsupportedProtocol {
+        protocol: Protocol,
+        operation: &'static str,
+    },
+}
+
+impl std::fmt::Display for DynamixelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DynamixelError::Io(err) => write!(f, "connection error: {}", err),
+            DynamixelError::Timeout => write!(f, "timed out waiting for a response"),
+            DynamixelError::InvalidPacket(reason) => write!(f, "invalid packet: {}", reason),
+            DynamixelError::Packet(err) => write!(f, "malformed response: {}", err),
+            DynamixelError::Status(errors) => write!(f, "servo reported errors: {:?}", errors),
+            DynamixelError::ProtocolTwoStatus { error, alert } => write!(
+                f,
+                "servo reported error: {:?}{}",
+                error,
+                if *alert { " (hardware alert)" } else { "" }
+            ),
+            DynamixelError::IDMismatch { expected, found } => write!(
+                f,
+                "expected a response from ID {} but found ID {}",
+                expected, found
+            ),
+            DynamixelError::UnknownModel(model) => write!(f, "unknown model number {}", model),
+            DynamixelError::UnknownItem(name) => write!(f, "unknown control table item {}", name),
+            DynamixelError::Broadcast => {
+                write!(f, "servos do not respond to broadcast instructions")
+            }
+            DynamixelError::Motion(failed) => {
+                write!(f, "motion cancelled as servos failed to stage:")?;
+                for (id, err) in failed.iter() {
+                    write!(f, " (ID {}: {})", id, err)?;
+                }
+                Ok(())
+            }
+            DynamixelError::WrongMode { mode, operation } => {
+                write!(f, "cannot {} while in {:?} mode", operation, mode)
+            }
+            DynamixelError::UnitMismatch {
+                name,
+                expected: Some(expected),
+                found,
+            } => write!(
+                f,
+                "item {} is measured in {:?}, not {:?}",
+                name, expected, found
+            ),
+            DynamixelError::UnitMismatch { name, .. } => {
+                write!(f, "item {} has no physical units", name)
+            }
+            DynamixelError::OutOfRange { name, value } => {
+                write!(f, "value {} is out of range for item {}", value, name)
+            }
+            DynamixelError::TooLarge { name, size, value } => write!(
+                f,
+                "value {} does not fit in the {} byte item {}",
+                value, size, name
+            ),
+            DynamixelError::ReadOnly(name) => write!(f, "item {} is read only", name),
+            DynamixelError::IDInUse(id) => write!(f, "ID {} is used by another servo", id),
+            DynamixelError::Unverified {
+                id,
+                baud_rate: Some(baud_rate),
+            } => write!(
+                f,
+                "servo did not respond at ID {} and {}bps after being changed",
+                id, baud_rate
+            ),
+            DynamixelError::Unverified { id, .. } => {
+                write!(f, "servo did not respond at ID {} after being changed", id)
+            }
+            DynamixelError::ServoCount(count) => write!(
+                f,
+                "expected a single new servo on the bus, but found {}",
+                count
+            ),
+            DynamixelError::ModelMismatch { expected, found } => write!(
+                f,
+                "backup is of model {}, but the servo is model {}",
+                expected, found
+            ),
+            DynamixelError::UnsupportedProtocol {
+                protocol,
+                operation,
+            } => write!(
+                f,
+                "cannot {} on a servo using protocol {}",
+                operation,
+                match protocol {
+                    Protocol::One => 1,
+                    Protocol::Two => 2,
+                }
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DynamixelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DynamixelError::Io(err) => Some(err),
+            DynamixelError::Packet(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for DynamixelError {
+    fn from(err: std::io::Error) -> DynamixelError {
+        // Serial ports signal a missing response by timing out or running dry
+        match err.kind() {
+            std::io::ErrorKind::TimedOut | std::io::ErrorKind::UnexpectedEof => {
+                DynamixelError::Timeout
+            }
+            _ => DynamixelError::Io(err),
+        }
+    }
+}
+
+impl From<protocol_one::PacketReadError> for DynamixelError {
+    fn from(err: protocol_one::PacketReadError) -> DynamixelError {
+        DynamixelError::Packet(err)
+    }
 }
 
 /// The abstract categories an item in the control table
 /// can be part of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ControlTableType {
     Sensor,
     ServoInformation,
     Component,
     Constraint,
+    Motion,
 }
 
 /// The levels of permission a user is granted in terms of an item in the
 /// control table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AccessLevel {
     Read,
     Write,
@@ -34,14 +263,18 @@ pub enum AccessLevel {
 /// A representation of an item in the control table, where only information
 /// is stored. When applicable, items in the control table are represented in
 /// this format, along with any optional data such as range or description.
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ControlTableData {
-    pub address: u8,
+    pub address: u16,
     pub size: u8,
     pub description: Option<String>,
     pub access: AccessLevel,
     pub initial_value: Option<String>,
-    pub range: Option<(u8, u8)>,
+    pub range: Option<(i64, i64)>,
     pub units: Option<sensor::DataUnit>,
+    /// Whether the item holds a two's complement value
+    #[serde(default)]
+    pub signed: bool,
 }
 
 /// An abstract representation of a Dynamixel servo
@@ -50,26 +283,64 @@ pub struct ControlTableData {
 /// - Servo Information (model, id)
 /// - Component (led, alarm)
 /// - Constraint (cw limit, max speed)
+/// - Motion (goal position, moving speed)
 /// The servo stores this abstracted representation of its control table
 /// within the aforementioned fields. Additionally, the structure stores
 /// an index of the control table (based on the data name column) to enable
-/// users to quickly locate a categorised item programmatically.
+/// users to quickly locate a categorised item programmatically. The complete
+/// control table of the servo's model is kept in `model` once it is known.
 ///
 /// Finally, the Dynamixel structure stores a list of packets if the
-/// `collects_packets` boolean is set to true.
+/// `collects_packets` boolean is set to true, along with the last status
+/// packet received and a `capture` of every byte exchanged (see
+/// [`capture`]). Any data received from the servo that has not yet formed a
+/// complete packet is held in the `decoder`, or in the `decoder_two` when
+/// communicating through protocol 2.
+///
+/// The ID of the servo is given when it is created, while its baud rate and
+/// firmware version are only known once they have been read from the servo.
+/// The servo is assumed to respond to every instruction until its status
+/// return level has been read or written. The last speed set through the
+/// `Motor` trait is stored in `speed`. The wheel or joint `mode` of the servo
+/// is only known once it has been read or set, and is forgotten whenever an
+/// item which decides it is written. The angle limits used in joint mode are
+/// kept in `joint_limits` while the servo is in wheel mode.
+/// Values written to an item outside of its range are handled according to
+/// the `range_policy`.
 ///
-/// Note that if you wish to broadcast to all servos, you will need to create
-/// an empty Dynamixel
+/// Each status packet is waited for according to the baud rate & the
+/// `return_delay` of the servo (500µs until its Return Delay Time is read),
+/// and instructions are resent according to the `retry_policy`. Every packet
+/// exchanged is counted in the `statistics`.
+/// To broadcast to all servos, create a Dynamixel with the
+/// `DynamixelID::Broadcast` ID; instructions are then sent without waiting
+/// for a status packet, and any instruction that needs one will fail.
 pub struct Dynamixel<C: Read + Write> {
     pub connection_handler: Box<C>,
+    pub id: DynamixelID,
+    pub baud_rate: Option<u32>,
+    pub firmware_version: Option<u8>,
+    pub status_return_level: StatusReturnLevel,
+    pub return_delay: std::time::Duration,
+    pub retry_policy: reliability::RetryPolicy,
+    pub statistics: reliability::Statistics,
+    pub range_policy: RangePolicy,
+    pub speed: usize,
+    pub mode: Option<DynamixelMode>,
+    pub joint_limits: Option<(u64, u64)>,
     pub control_table: HashMap<String, ControlTableType>,
     pub sensors: HashMap<String, Box<dyn DataSensor<isize>>>,
-    pub components: HashMap<String, ()>, // should become a custom datatype/enum
+    pub sensor_cache: telemetry::SensorCache,
+    pub components: HashMap<String, component::ComponentType>,
     pub information: HashMap<String, ControlTableData>,
     pub constraints: HashMap<String, ControlTableData>,
+    pub model: Option<control_table::Model>,
     pub last_packet: Option<Packet>,
     pub sent_packets: Vec<Packet>,
     pub collects_packets: bool,
+    pub capture: capture::Capture,
+    pub decoder: protocol_one::PacketDecoder,
+    pub decoder_two: protocol_two::PacketDecoder,
 }
 
 impl<C> Dynamixel<C>
@@ -79,6 +350,7 @@ where
     /// Create a new Dynamixel servo
     pub fn new(
         connection_handler: C,
+        id: DynamixelID,
         control_table: HashMap<String, ControlTableType>,
         sensors: HashMap<String, Box<dyn DataSensor<isize>>>,
         information: HashMap<String, ControlTableData>,
@@ -87,29 +359,297 @@ where
     ) -> Self {
         Dynamixel {
             connection_handler: Box::new(connection_handler),
+            id,
+            baud_rate: None,
+            firmware_version: None,
+            status_return_level: StatusReturnLevel::All,
+            return_delay: reliability::DEFAULT_RETURN_DELAY,
+            retry_policy: reliability::RetryPolicy::default(),
+            statistics: reliability::Statistics::new(),
+            range_policy: RangePolicy::Error,
+            speed: 0,
+            mode: None,
+            joint_limits: None,
             control_table,
             sensors,
+            sensor_cache: telemetry::SensorCache::new(),
             components: HashMap::new(),
             information,
             constraints,
+            model: None,
             last_packet: None,
             sent_packets: vec![],
             collects_packets,
+            capture: capture::Capture::new(),
+            decoder: protocol_one::PacketDecoder::new(),
+            decoder_two: protocol_two::PacketDecoder::new(),
         }
     }
 
     // HACK: Should be removed when sensors are completed
-    pub fn new_empty(connection_handler: C) -> Self {
+    pub fn new_empty(connection_handler: C, id: DynamixelID) -> Self {
         Dynamixel {
             connection_handler: Box::new(connection_handler),
+            id,
+            baud_rate: None,
+            firmware_version: None,
+            status_return_level: StatusReturnLevel::All,
+            return_delay: reliability::DEFAULT_RETURN_DELAY,
+            retry_policy: reliability::RetryPolicy::default(),
+            statistics: reliability::Statistics::new(),
+            range_policy: RangePolicy::Error,
+            speed: 0,
+            mode: None,
+            joint_limits: None,
             control_table: HashMap::new(),
             sensors: HashMap::new(),
+            sensor_cache: telemetry::SensorCache::new(),
             components: HashMap::new(),
             information: HashMap::new(),
             constraints: HashMap::new(),
+            model: None,
             last_packet: None,
             sent_packets: vec![],
             collects_packets: false,
+            capture: capture::Capture::new(),
+            decoder: protocol_one::PacketDecoder::new(),
+            decoder_two: protocol_two::PacketDecoder::new(),
+        }
+    }
+
+    /// Loads the control table of a model, indexing each of its items by
+    /// category and creating a sensor for each of its sensor items. Any
+    /// previously loaded control table is replaced, along with its sensors
+    /// and the values read for them.
+    pub fn set_model(&mut self, model: control_table::Model) {
+        self.control_table.clear();
+        self.sensors.clear();
+        self.sensor_cache = telemetry::SensorCache::new();
+        self.components.clear();
+        self.information.clear();
+        self.constraints.clear();
+
+        for item in model.items.iter() {
+            self.control_table.insert(item.name.clone(), item.category);
+            match item.category {
+                ControlTableType::ServoInformation => {
+                    self.information
+                        .insert(item.name.clone(), item.data.clone());
+                }
+                ControlTableType::Constraint => {
+                    self.constraints
+                        .insert(item.name.clone(), item.data.clone());
+                }
+                ControlTableType::Component => {
+                    self.components
+                        .insert(item.name.clone(), component::ComponentType::of(&item.name));
+                }
+                ControlTableType::Sensor | ControlTableType::Motion => {}
+            }
+        }
+
+        self.model = Some(model);
+        self.load_sensors();
+    }
+
+    /// The ID of the servo, which cannot be the broadcast ID when a status
+    /// packet is expected in response
+    fn responding_id(&self) -> Result<u8, DynamixelError> {
+        match self.id {
+            DynamixelID::Broadcast => Err(DynamixelError::Broadcast),
+            DynamixelID::ID(id) => Ok(id),
+        }
+    }
+
+    /// The protocol of the servo's model. Servos whose model is not known
+    /// are assumed to use protocol 1.
+    pub fn protocol(&self) -> Protocol {
+        self.model
+            .as_ref()
+            .map_or(Protocol::One, |model| model.protocol)
+    }
+
+    /// Rejects an operation which is only implemented for protocol 1 servos
+    fn require_protocol_one(&self, operation: &'static str) -> Result<(), DynamixelError> {
+        match self.protocol() {
+            Protocol::One => Ok(()),
+            protocol => Err(DynamixelError::UnsupportedProtocol {
+                protocol,
+                operation,
+            }),
+        }
+    }
+
+    /// Reads an item by name, using the protocol of the servo's model
+    fn read_model_item(&mut self, name: &str) -> Result<u64, DynamixelError> {
+        match self.protocol() {
+            Protocol::One => protocol_one::ProtocolOne::read_item(self, name),
+            Protocol::Two => protocol_two::ProtocolTwo::read_item(self, name),
+        }
+    }
+
+    /// Writes an item by name, using the protocol of the servo's model
+    fn write_model_item(&mut self, name: &str, value: u64) -> Result<(), DynamixelError> {
+        match self.protocol() {
+            Protocol::One => protocol_one::ProtocolOne::write_item(self, name, value),
+            Protocol::Two => protocol_two::ProtocolTwo::write_item(self, name, value),
+        }
+    }
+
+    /// Finds an item in the control table of the servo's model by name
+    pub fn get_item(&self, name: &str) -> Result<&ControlTableData, DynamixelError> {
+        self.model
+            .as_ref()
+            .and_then(|model| model.item(name))
+            .map(|item| &item.data)
+            .ok_or_else(|| DynamixelError::UnknownItem(name.to_string()))
+    }
+
+    /// Checks that a value can be written to an item, returning the value to
+    /// write. Writes to read only items & values needing more bytes than the
+    /// item holds are always rejected, while values outside of the item's
+    /// range are rejected or clamped according to the `range_policy`.
+    ///
+    /// Items with a negative range take values in two's complement, either
+    /// in the size of the item or of the whole `u64`.
+    ///
+    /// ```
+    /// use movement::dynamixel::control_table::builtin_model;
+    /// use movement::dynamixel::{Dynamixel, DynamixelError, DynamixelID, RangePolicy};
+    ///
+    /// fn main() {
+    ///     let mut dxl = Dynamixel::new_empty(std::io::empty(), DynamixelID::ID(1));
+    ///     dxl.set_model(builtin_model(12).unwrap());
+    ///
+    ///     let result = dxl.check_write("Present Position", 512);
+    ///     assert!(matches!(result, Err(DynamixelError::ReadOnly(_))));
+    ///     let result = dxl.check_write("LED", 256);
+    ///     assert!(matches!(result, Err(DynamixelError::TooLarge { .. })));
+    ///     let result = dxl.check_write("Goal Position", 2000);
+    ///     assert!(matches!(result, Err(DynamixelError::OutOfRange { .. })));
+    ///
+    ///     dxl.range_policy = RangePolicy::Clamp;
+    ///     assert_eq!(dxl.check_write("Goal Position", 2000).unwrap(), 1023);
+    /// }
+    /// ```
+    pub fn check_write(&self, name: &str, value: u64) -> Result<u64, DynamixelError> {
+        let data = self.get_item(name)?;
+        if data.access == AccessLevel::Read {
+            return Err(DynamixelError::ReadOnly(name.to_string()));
+        }
+
+        let bits = 8 * data.size as u32;
+        let mask = (1u64 << bits) - 1;
+        let signed = data.signed || matches!(data.range, Some((min, _)) if min < 0);
+        let number = if value <= mask {
+            if signed {
+                // Sign extend the value from the size of the item
+                ((value << (64 - bits)) as i64) >> (64 - bits)
+            } else {
+                value as i64
+            }
+        } else if signed && (value as i64) < 0 && (value as i64) >= -(1 << (bits - 1)) {
+            value as i64
+        } else {
+            return Err(DynamixelError::TooLarge {
+                name: name.to_string(),
+                size: data.size,
+                value,
+            });
+        };
+
+        let number = match data.range {
+            Some((min, max)) if number < min || number > max => match self.range_policy {
+                RangePolicy::Clamp => number.clamp(min, max),
+                RangePolicy::Error => {
+                    return Err(DynamixelError::OutOfRange {
+                        name: name.to_string(),
+                        value: number,
+                    })
+                }
+            },
+            _ => number,
+        };
+
+        Ok(number as u64 & mask)
+    }
+
+    /// Sizes a value to the named item
+    fn item_bytes(&self, name: &str, value: u64) -> Result<DataBytes, DynamixelError> {
+        let data = self.get_item(name)?;
+        let signed = data.signed || matches!(data.range, Some((min, _)) if min < 0);
+
+        DataBytes::sized(value, data.size, signed).ok_or_else(|| {
+            DynamixelError::InvalidPacket(format!(
+                "Item {} has an invalid size of {} bytes!",
+                name, data.size
+            ))
+        })
+    }
+
+    /// Writes a value to the named item with `write`, keeping the status
+    /// return level, return delay & mode of the servo in step with the write.
+    /// The servo responds according to its new status return level, so that
+    /// is changed before the write is sent and restored if it fails.
+    fn write_item_with<F>(&mut self, name: &str, value: u64, write: F) -> Result<(), DynamixelError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), DynamixelError>,
+    {
+        let status_return_level = self.status_return_level;
+        if name == "Status Return Level" {
+            self.status_return_level = StatusReturnLevel::from(value as u8);
+        }
+
+        let result = write(self);
+
+        // The mode depends on the angle limits in protocol 1 & the Operating
+        // Mode in protocol 2, so it must be read again. A write which failed
+        // may still have reached the servo.
+        if name.ends_with("Angle Limit") || name == "Operating Mode" {
+            self.mode = None;
+        }
+
+        if let Err(err) = result {
+            self.status_return_level = status_return_level;
+            return Err(err);
+        }
+
+        // Both protocols count the Return Delay Time in units of 2µs
+        if name == "Return Delay Time" {
+            self.return_delay = std::time::Duration::from_micros(2 * value);
+        }
+
+        Ok(())
+    }
+}
+
+/// How values outside of the range of an item are written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangePolicy {
+    /// The write is rejected with `DynamixelError::OutOfRange`
+    Error,
+    /// The value is clamped to the nearest end of the range
+    Clamp,
+}
+
+/// The instructions a servo responds to with a status packet, as set by its
+/// Status Return Level item
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusReturnLevel {
+    /// Only ping instructions are responded to
+    PingOnly,
+    /// Ping & read instructions are responded to
+    Read,
+    /// Every instruction is responded to, which is the factory setting
+    All,
+}
+
+impl From<u8> for StatusReturnLevel {
+    fn from(value: u8) -> StatusReturnLevel {
+        match value {
+            0 => StatusReturnLevel::PingOnly,
+            1 => StatusReturnLevel::Read,
+            _ => StatusReturnLevel::All,
         }
     }
 }
@@ -117,13 +657,14 @@ where
 /// A representation of the 2 movement states a Dynamixel can be in:
 /// - Wheel
 /// - Joint
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DynamixelMode {
     Wheel,
     Joint,
 }
 
 /// Packets can either be addressed to a single Dynamixel or all dynamixels
-#[derive(Clone, Copy, PartialEq, Eq, Hash)] // TODO: Remove these derives
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)] // TODO: Remove these derives
 pub enum DynamixelID {
     Broadcast,
     ID(u8),
@@ -141,24 +682,44 @@ impl From<DynamixelID> for u8 {
 
 // TODO: Rename this to something better
 pub trait PacketManipulation {
-    fn checksum(id: &u8, length: &u8, parameters: &Vec<u8>, opcode: &u8) -> u8;
     fn generate(&self) -> Result<Vec<u8>, String>;
 }
 
+impl<P: PacketManipulation> PacketManipulation for &P {
+    fn generate(&self) -> Result<Vec<u8>, String> {
+        (**self).generate()
+    }
+}
+
 // Remove 'get' prefix?
+/// Information identifying a servo. Values other than the ID are `None`
+/// until they have been read from the servo (see `read_information` in
+/// either protocol).
 pub trait DynamixelInformation {
     fn get_id(&self) -> DynamixelID;
-    // fn get_baudrate(&self) -> u64;
+    fn get_baudrate(&self) -> Option<u32>;
+    fn get_model_number(&self) -> Option<u16>;
+    fn get_firmware_version(&self) -> Option<u8>;
 }
 
-// return values should be wrappen in Option
 impl<C> DynamixelInformation for Dynamixel<C>
 where
     C: Read + Write,
 {
     fn get_id(&self) -> DynamixelID {
-        // Temporary until this can be properly implemented later
-        DynamixelID::ID(1)
+        self.id
+    }
+
+    fn get_baudrate(&self) -> Option<u32> {
+        self.baud_rate
+    }
+
+    fn get_model_number(&self) -> Option<u16> {
+        self.model.as_ref().map(|model| model.number)
+    }
+
+    fn get_firmware_version(&self) -> Option<u8> {
+        self.firmware_version
     }
 }
 
@@ -189,7 +750,9 @@ macro_rules! impl_to_databytes {
     };
 }
 
-#[derive(Debug)]
+/// A value sized to an item of the control table, which is read or written
+/// using every byte of the item
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DataBytes {
     One(u8),
     OneSigned(i8),
@@ -216,6 +779,68 @@ impl From<DataBytes> for Vec<u8> {
     }
 }
 
+impl DataBytes {
+    /// Sizes a value to an item of 1, 2 or 4 bytes, keeping only the bytes
+    /// of the value which fit in the item. Signed items take values in two's
+    /// complement.
+    ///
+    /// ```
+    /// use movement::dynamixel::DataBytes;
+    ///
+    /// fn main() {
+    ///     assert_eq!(DataBytes::sized(500, 2, false), Some(DataBytes::Two(500)));
+    ///     assert_eq!(DataBytes::sized(0xFFFF, 2, true), Some(DataBytes::TwoSigned(-1)));
+    ///     assert_eq!(DataBytes::sized(0, 3, false), None);
+    /// }
+    /// ```
+    pub fn sized(value: u64, size: u8, signed: bool) -> Option<DataBytes> {
+        let data = match (size, signed) {
+            (1, false) => DataBytes::One(value as u8),
+            (1, true) => DataBytes::OneSigned(value as i8),
+            (2, false) => DataBytes::Two(value as u16),
+            (2, true) => DataBytes::TwoSigned(value as i16),
+            (4, false) => DataBytes::Four(value as u32),
+            (4, true) => DataBytes::FourSigned(value as i32),
+            _ => return None,
+        };
+
+        Some(data)
+    }
+
+    /// Decodes a value from the little endian bytes of an item
+    pub fn decode(bytes: &[u8], signed: bool) -> Option<DataBytes> {
+        match bytes.len() {
+            1 | 2 | 4 => {
+                let value = LittleEndian::read_uint(bytes, bytes.len());
+                DataBytes::sized(value, bytes.len() as u8, signed)
+            }
+            _ => None,
+        }
+    }
+
+    /// The number of bytes in the value
+    pub fn size(&self) -> u8 {
+        match self {
+            DataBytes::One(_) | DataBytes::OneSigned(_) => 1,
+            DataBytes::Two(_) | DataBytes::TwoSigned(_) => 2,
+            DataBytes::Four(_) | DataBytes::FourSigned(_) => 4,
+        }
+    }
+}
+
+impl From<DataBytes> for i64 {
+    fn from(bytes: DataBytes) -> i64 {
+        match bytes {
+            DataBytes::One(n) => n.into(),
+            DataBytes::OneSigned(n) => n.into(),
+            DataBytes::Two(n) => n.into(),
+            DataBytes::TwoSigned(n) => n.into(),
+            DataBytes::Four(n) => n.into(),
+            DataBytes::FourSigned(n) => n.into(),
+        }
+    }
+}
+
 // impl From<&DataBytes> for Vec<u8> {
 //     fn from(bytes: &DataBytes) -> Vec<u8> {
 //         let mut buf: Vec<u8> = Vec::new();
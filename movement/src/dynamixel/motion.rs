@@ -0,0 +1,220 @@
+//! # Coordinated motion
+//! Sending a goal position to each servo in turn means that the first servo
+//! starts moving well before the last. Instead, a `MotionTransaction` stages
+//! the goal position & moving speed of every servo with a registered write,
+//! then starts them all at the same instant with a single broadcast action.
+//!
+//! Every registered write must be acknowledged before the motion starts, so
+//! each servo must respond to every instruction (a Status Return Level of 2).
+//! If any servo fails to stage its move, the moves already staged are
+//! replaced with the servo's original goals and actioned individually, which
+//! clears their Registered flag without moving any servo.
+//!
+//! This is implemented for protocol 1 servos, where the goal position &
+//! moving speed are found at the same addresses on every model.
+//!
+//! ```
+//! use movement::dynamixel::bus::DynamixelBus;
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::motion::MotionTransaction;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//!
+//! fn main() {
+//!     let mut port = SimulatedBus::new();
+//!     port.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     port.add_servo(SimulatedDynamixel::new(2, builtin_model(12).unwrap()));
+//!     let bus = DynamixelBus::new(port);
+//!
+//!     MotionTransaction::new()
+//!         .goal_position(1, 200)
+//!         .goal_position(2, 800)
+//!         .moving_speed(2, 100)
+//!         .execute(&bus)
+//!         .unwrap();
+//!
+//!     bus.transaction(|port| {
+//!         assert_eq!(port.servo(1).unwrap().get_item("Goal Position"), Some(200));
+//!         assert_eq!(port.servo(2).unwrap().get_item("Moving Speed"), Some(100));
+//!     });
+//!
+//!     // Servo 3 does not exist, so servo 1 does not move
+//!     let result = MotionTransaction::new()
+//!         .goal_position(1, 400)
+//!         .goal_position(3, 400)
+//!         .execute(&bus);
+//!     assert!(result.is_err());
+//!
+//!     bus.transaction(|port| {
+//!         assert_eq!(port.servo(1).unwrap().get_item("Goal Position"), Some(200));
+//!         assert_eq!(port.servo(1).unwrap().get_item("Registered"), Some(0));
+//!     });
+//! }
+//! ```
+
+use super::bus::{BusConnection, DynamixelBus};
+use super::protocol_one::ProtocolOne;
+use super::{DataBytes, Dynamixel, DynamixelError, DynamixelID};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+/// The address of the Goal Position item, which is directly followed by the
+/// Moving Speed item
+const GOAL_POSITION: u8 = 30;
+
+/// The goal position and/or moving speed to stage on a single servo
+#[derive(Clone, Copy, Debug, Default)]
+struct Move {
+    goal_position: Option<u16>,
+    moving_speed: Option<u16>,
+}
+
+impl Move {
+    /// The address & length of the registered write needed for the move
+    fn location(&self) -> (u8, u8) {
+        match (self.goal_position, self.moving_speed) {
+            (Some(_), Some(_)) => (GOAL_POSITION, 4),
+            (None, Some(_)) => (GOAL_POSITION + 2, 2),
+            _ => (GOAL_POSITION, 2),
+        }
+    }
+
+    /// The data to register, with any values not being changed taken from
+    /// the values currently on the servo
+    fn data(&self, current: &[u8]) -> Vec<u8> {
+        let mut data = current.to_vec();
+        if let Some(position) = self.goal_position {
+            LittleEndian::write_u16(&mut data[0..2], position);
+        }
+        if let Some(speed) = self.moving_speed {
+            LittleEndian::write_u16(&mut data[2..4], speed);
+        }
+
+        let (address, length) = self.location();
+        let start = (address - GOAL_POSITION) as usize;
+        data[start..start + length as usize].to_vec()
+    }
+}
+
+/// A set of moves which are started on several servos at the same instant
+#[derive(Clone, Debug, Default)]
+pub struct MotionTransaction {
+    moves: BTreeMap<u8, Move>,
+}
+
+impl MotionTransaction {
+    /// Creates a transaction without any moves
+    pub fn new() -> MotionTransaction {
+        MotionTransaction::default()
+    }
+
+    /// Sets the goal position of a servo
+    pub fn goal_position(mut self, id: u8, position: u16) -> MotionTransaction {
+        self.moves.entry(id).or_default().goal_position = Some(position);
+        self
+    }
+
+    /// Sets the moving speed of a servo
+    pub fn moving_speed(mut self, id: u8, speed: u16) -> MotionTransaction {
+        self.moves.entry(id).or_default().moving_speed = Some(speed);
+        self
+    }
+
+    /// Stages every move, then starts them with a broadcast action. The bus
+    /// is held for the whole transaction, so no other servo can use it until
+    /// the motion has started or been rolled back.
+    ///
+    /// Each instruction is sent through the servo's handle on the bus, so is
+    /// resent according to the retry policy of the bus & counted in its
+    /// statistics. If any servo still fails to stage its move, the motion is
+    /// rolled back and a `DynamixelError::Motion` listing each failed servo
+    /// is returned.
+    pub fn execute<C>(self, bus: &DynamixelBus<C>) -> Result<(), DynamixelError>
+    where
+        C: Read + Write,
+    {
+        if self.moves.contains_key(&DynamixelID::Broadcast.into()) {
+            return Err(DynamixelError::InvalidPacket(String::from(
+                "Cannot stage a move on the broadcast ID!",
+            )));
+        }
+
+        bus.hold(|| {
+            let mut staged = vec![];
+            let mut failed = vec![];
+
+            for (&id, motion) in self.moves.iter() {
+                let address = motion.location().0;
+                match stage(bus.servo(id).held(), motion) {
+                    Ok(original) => staged.push((id, address, original)),
+                    Err((_, DynamixelError::Io(err))) => return Err(DynamixelError::Io(err)),
+                    Err((original, err)) => {
+                        // The move may have been staged even though it was
+                        // not acknowledged, so it is rolled back regardless
+                        if let Some(original) = original {
+                            staged.push((id, address, original));
+                        }
+                        failed.push((id, err));
+                    }
+                }
+            }
+
+            if failed.is_empty() {
+                return bus.broadcast().held().action();
+            }
+
+            // Overwrite each staged move with the original goal, then action
+            // it immediately, which leaves the servo where it was
+            for (id, address, original) in staged {
+                let mut servo = bus.servo(id);
+                let dxl = servo.held();
+                let result = dxl
+                    .register_write(address, data_bytes(&original))
+                    .and_then(|_| dxl.action());
+                match result {
+                    Ok(_) => {}
+                    Err(DynamixelError::Io(err)) => return Err(DynamixelError::Io(err)),
+                    Err(err) if !failed.iter().any(|(failed_id, _)| *failed_id == id) => {
+                        failed.push((id, err))
+                    }
+                    Err(_) => {}
+                }
+            }
+
+            Err(DynamixelError::Motion(failed))
+        })
+    }
+}
+
+/// Registers a move on a single servo, returning the original data at the
+/// registered address so that it can be restored. If staging fails, the
+/// original data is returned alongside the error once it is known.
+fn stage<C>(
+    dxl: &mut Dynamixel<BusConnection<C>>,
+    motion: &Move,
+) -> Result<Vec<u8>, (Option<Vec<u8>>, DynamixelError)>
+where
+    C: Read + Write,
+{
+    let current = dxl
+        .read(GOAL_POSITION, 4)
+        .map_err(|err| (None, err))?
+        .parameters;
+
+    let (address, length) = motion.location();
+    let start = (address - GOAL_POSITION) as usize;
+    let original = current[start..start + length as usize].to_vec();
+
+    match dxl.register_write(address, data_bytes(&motion.data(&current))) {
+        Ok(_) => Ok(original),
+        Err(err) => Err((Some(original), err)),
+    }
+}
+
+/// The goal position and/or moving speed held in 2 or 4 little endian bytes
+fn data_bytes(data: &[u8]) -> DataBytes {
+    match data.len() {
+        4 => DataBytes::Four(LittleEndian::read_u32(data)),
+        _ => DataBytes::Two(LittleEndian::read_u16(data)),
+    }
+}
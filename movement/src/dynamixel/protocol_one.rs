@@ -3,11 +3,18 @@
 //! communicate with Robotis 'Dynamixel' servos via their
 //! [Protocol 1.0](https://emanual.robotis.com/docs/en/dxl/protocol1/)
 
-use super::{DynamixelInformation, PacketManipulation};
+use super::capture::RecordingReader;
+use super::dissector::to_hex;
+use super::servo_connection::{drain, read_packet, DeadlineReader};
+use super::{
+    DataBytes, DynamixelError, DynamixelID, DynamixelInformation, PacketManipulation,
+    StatusReturnLevel,
+};
 use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::TryFrom;
 use std::io::{Read, Write};
+use std::time::Duration;
 
 /// The types of instructions that can be sent to a Dynamixel.
 #[derive(Copy, Clone, Debug)]
@@ -60,7 +67,7 @@ impl TryFrom<u8> for InstructionType {
 /// The types of statuses that can be returned by a Dynamixel, as stored
 /// with each bit representing a different error. For more info, see
 /// <https://emanual.robotis.com/docs/en/dxl/protocol1/#status-packetreturn-packet>
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum StatusType {
     // This needs work - maybe a Result to represent either success or failure?
     // It should not be possible to have Success and Overload at the same time.
@@ -75,27 +82,40 @@ pub enum StatusType {
 }
 
 impl StatusType {
+    /// The bit of the error byte which is set for each error, as given in the
+    /// e-manual
+    fn bit(&self) -> Option<u8> {
+        match self {
+            StatusType::Success => None,
+            StatusType::InputVoltage => Some(0),
+            StatusType::AngleLimit => Some(1),
+            StatusType::Overheating => Some(2),
+            StatusType::Range => Some(3),
+            StatusType::Checksum => Some(4),
+            StatusType::Overload => Some(5),
+            StatusType::Instruction => Some(6),
+        }
+    }
+
     /// Gets the numeric representation of an error code given the list of
     /// errors
+    ///
+    /// ```
+    /// use movement::dynamixel::protocol_one::StatusType;
+    ///
+    /// fn main() {
+    ///     let errors = vec![StatusType::InputVoltage, StatusType::Overload];
+    ///     assert_eq!(StatusType::get_error_code(&errors), 0b0010_0001);
+    ///     assert_eq!(StatusType::get_error_types(&0b0010_0001), errors);
+    /// }
+    /// ```
     pub fn get_error_code(errors: &Vec<StatusType>) -> u8 {
         let mut error_code = 0u8;
 
         for err in errors {
-            let index = match err {
-                StatusType::Success => return 0,
-                StatusType::Instruction => 1,
-                StatusType::Overload => 2,
-                StatusType::Checksum => 3,
-                StatusType::Range => 4,
-                StatusType::Overheating => 5,
-                StatusType::AngleLimit => 6,
-                StatusType::InputVoltage => 7,
-            };
-
-            if index != 0 {
-                error_code |= 1 << index;
-            } else {
-                return 0;
+            match err.bit() {
+                Some(bit) => error_code |= 1 << bit,
+                None => return 0,
             }
         }
 
@@ -109,13 +129,13 @@ impl StatusType {
         for i in 0..8 {
             if error & (1 << i) != 0 {
                 let error_type: Option<StatusType> = match i {
-                    1 => Some(StatusType::Instruction),
-                    2 => Some(StatusType::Overload),
-                    3 => Some(StatusType::Checksum),
-                    4 => Some(StatusType::Range),
-                    5 => Some(StatusType::Overheating),
-                    6 => Some(StatusType::AngleLimit),
-                    7 => Some(StatusType::InputVoltage),
+                    0 => Some(StatusType::InputVoltage),
+                    1 => Some(StatusType::AngleLimit),
+                    2 => Some(StatusType::Overheating),
+                    3 => Some(StatusType::Range),
+                    4 => Some(StatusType::Checksum),
+                    5 => Some(StatusType::Overload),
+                    6 => Some(StatusType::Instruction),
                     _ => None,
                 };
 
@@ -147,12 +167,12 @@ pub struct Packet {
     checksum: u8,
 }
 
-impl PacketManipulation for Packet {
+impl Packet {
     /// Calculates the checksum for the packet
-    fn checksum(id: &u8, length: &u8, parameters: &Vec<u8>, opcode: &u8) -> u8 {
-        let mut sum: usize = *id as usize + *length as usize;
+    pub fn checksum(id: u8, length: u8, parameters: &[u8], opcode: u8) -> u8 {
+        let mut sum: usize = id as usize + length as usize;
         sum += parameters.iter().map(|i| *i as usize).sum::<usize>();
-        sum += *opcode as usize;
+        sum += opcode as usize;
 
         let chk: u8 = if sum > 255 {
             (sum as u8) & 0xFF
@@ -163,22 +183,66 @@ impl PacketManipulation for Packet {
         !chk
     }
 
+    /// Serialises the packet regardless of its type. Status packets are
+    /// only ever sent by servos, so [`PacketManipulation::generate`] should
+    /// be preferred when communicating with a real servo.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let opcode = match self.packet_type {
+            PacketType::Instruction(inst) => u8::from(inst),
+            PacketType::Status(ref status) => StatusType::get_error_code(status),
+        };
+
+        let mut packet = vec![255, 255, self.id, self.length, opcode];
+        packet.extend(&self.parameters);
+        packet.push(self.checksum);
+
+        packet
+    }
+}
+
+impl std::fmt::Display for Packet {
+    /// Summarises the packet without reference to a control table. See
+    /// [`dissector`](super::dissector) to name the items it addresses.
+    ///
+    /// ```
+    /// use movement::dynamixel::protocol_one::Packet;
+    ///
+    /// fn main() {
+    ///     let pck = Packet::read(1, 36, 2);
+    ///     assert_eq!(pck.to_string(), "ID 1 Read: 24 02");
+    /// }
+    /// ```
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.packet_type {
+            PacketType::Instruction(instruction) => write!(f, "ID {} {:?}", self.id, instruction)?,
+            PacketType::Status(ref errors) if errors.is_empty() => {
+                write!(f, "ID {} Status OK", self.id)?
+            }
+            PacketType::Status(ref errors) => write!(f, "ID {} Status {:?}", self.id, errors)?,
+        }
+
+        if !self.parameters.is_empty() {
+            write!(f, ": {}", to_hex(&self.parameters))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PacketManipulation for Packet {
     /// Provides packet-crafting functionality for servo communication. If you want
     /// to actually write to the servo, see the ConnectionHandler trait (TODO: LINK).
     fn generate(&self) -> Result<Vec<u8>, String> {
-        if let PacketType::Instruction(instruction) = self.packet_type {
-            let mut packet = vec![255, 255, self.id, self.length, instruction.into()];
-            packet.extend(&self.parameters);
-            packet.push(self.checksum);
-
-            Ok(packet)
+        if let PacketType::Instruction(_) = self.packet_type {
+            Ok(self.to_bytes())
         } else {
             Err("You cannot write a status packet to a servo!".to_string())
         }
     }
 }
 
-#[derive(Debug)]
+/// The ways in which a packet read from a servo can be malformed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PacketReadError {
     InvalidLength,
     InvalidHeader,
@@ -186,6 +250,19 @@ pub enum PacketReadError {
     InvalidInstruction,
 }
 
+impl std::fmt::Display for PacketReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PacketReadError::InvalidLength => write!(f, "packet has an invalid length"),
+            PacketReadError::InvalidHeader => write!(f, "packet has an invalid header"),
+            PacketReadError::InvalidChecksum => write!(f, "packet has an invalid checksum"),
+            PacketReadError::InvalidInstruction => write!(f, "packet has an invalid instruction"),
+        }
+    }
+}
+
+impl std::error::Error for PacketReadError {}
+
 impl Packet {
     pub fn from_vec(
         vec: Vec<u8>,
@@ -199,14 +276,10 @@ impl Packet {
                     return Err(PacketReadError::InvalidLength);
                 }
             }
-            InstructionType::Read => {
-                if vec.len()
-                    != 6 + length
-                        .expect("Must pass length parameter for all applicable instructions!")
-                {
-                    return Err(PacketReadError::InvalidLength);
-                }
-            }
+            InstructionType::Read => match length {
+                Some(length) if vec.len() == 6 + length => {}
+                _ => return Err(PacketReadError::InvalidLength),
+            },
             InstructionType::Write => {}
             InstructionType::RegWrite => {}
             InstructionType::Action => {}
@@ -216,6 +289,11 @@ impl Packet {
             InstructionType::BulkRead => {}
         };
 
+        // Every packet has at least a header, ID, length, error & checksum
+        if vec.len() < 6 {
+            return Err(PacketReadError::InvalidLength);
+        }
+
         // Validate header
         if vec[0..2] != [0xFF, 0xFF] {
             return Err(PacketReadError::InvalidHeader);
@@ -224,10 +302,15 @@ impl Packet {
         // Extract packet data
         let (id, length, error) = (vec[2], vec[3], vec[4]);
         let params: Vec<u8> = vec[5..vec.len() - 1].to_vec();
-        let chk = vec.last().unwrap();
+        let chk = vec[vec.len() - 1];
+
+        // Validate length
+        if length as usize != params.len() + 2 {
+            return Err(PacketReadError::InvalidLength);
+        }
 
         // Validate checksum
-        if *chk != Packet::checksum(&id, &length, &params, &error) {
+        if chk != Packet::checksum(id, length, &params, error) {
             return Err(PacketReadError::InvalidChecksum);
         }
 
@@ -244,7 +327,7 @@ impl Packet {
             PacketType::Instruction(inst) => u8::from(inst),
             PacketType::Status(ref status) => StatusType::get_error_code(&status),
         };
-        let checksum = Packet::checksum(&id, &(parameters.len() as u8 + 2u8), &parameters, &opcode);
+        let checksum = Packet::checksum(id, parameters.len() as u8 + 2u8, &parameters, opcode);
 
         let packet = Packet {
             id,
@@ -257,7 +340,10 @@ impl Packet {
         packet
     }
 
-    /// Creates a new protocol 1 packet
+    /// Creates a new protocol 1 packet, where each parameter is stored in
+    /// the fewest bytes which can represent it. As the size of an item is
+    /// lost, values written to the control table should be given to
+    /// `Packet::write` as `DataBytes` instead.
     ///
     /// ```
     /// use movement::dynamixel::PacketManipulation;
@@ -266,13 +352,15 @@ impl Packet {
     /// fn main() {
     ///     let pck = Packet::new(1, PacketType::Instruction(InstructionType::Write), vec![25, 1]);
     ///     assert_eq!(pck.generate().unwrap(), [255, 255, 1, 4, 3, 25, 1, 221]);
+    ///
+    ///     // The checksum covers both bytes of a value over 255
+    ///     let pck = Packet::new(1, PacketType::Instruction(InstructionType::Write), vec![30, 500]);
+    ///     assert_eq!(pck.generate().unwrap(), [255, 255, 1, 5, 3, 30, 244, 1, 227]);
     /// }
     /// ```
     pub fn new(id: u8, packet_type: PacketType, parameters: Vec<u64>) -> Packet {
-        // Convert all given parameters into little-endian format
-        // Also determines the minimum amount of bytes needed to represent data
-        // Apparently some of the data is signed? need to investigate...
-        // TODO: Add test to make sure the min-bytes functionality works
+        // Convert all given parameters into little-endian format, using the
+        // minimum amount of bytes needed to represent each
         let mut new_params: Vec<u8> = vec![];
 
         for i in parameters.iter() {
@@ -292,7 +380,7 @@ impl Packet {
             PacketType::Instruction(inst) => u8::from(inst),
             PacketType::Status(ref status) => StatusType::get_error_code(&status),
         };
-        let checksum = Packet::checksum(&id, &(parameters.len() as u8 + 2u8), &new_params, &opcode);
+        let checksum = Packet::checksum(id, new_params.len() as u8 + 2u8, &new_params, opcode);
 
         let packet = Packet {
             id,
@@ -306,160 +394,598 @@ impl Packet {
     }
 }
 
-/// This trait exposes all functionality possessed by Protocol One servos. For
-/// more information, please refer to <https://emanual.robotis.com/docs/en/dxl/protocol1/#instruction-details>
-// TODO: Refactor doctests using fully-implemented API
-// TODO: Fix number sizes
-pub trait ProtocolOne {
-    /// Creates a packet to ping the dynamixel, returning the crafted packet
+impl Packet {
+    /// Creates a packet to ping a servo
+    pub fn ping(id: u8) -> Packet {
+        Packet::new_raw(id, PacketType::Instruction(InstructionType::Ping), vec![])
+    }
+
+    /// Creates a packet to read `length` bytes from an address on a servo
+    pub fn read(id: u8, address: u8, length: u8) -> Packet {
+        Packet::new_raw(
+            id,
+            PacketType::Instruction(InstructionType::Read),
+            vec![address, length],
+        )
+    }
+
+    /// Creates a packet to write a value to an address on a servo
     ///
-    /// This function implements section [4.1](https://emanual.robotis.com/docs/en/dxl/protocol1/#ping)
     /// ```
-    /// use movement::dynamixel::{Dynamixel, Packet, DynamixelID, protocol_one::ProtocolOne};
+    /// use movement::dynamixel::{DataBytes, PacketManipulation};
+    /// use movement::dynamixel::protocol_one::Packet;
     ///
     /// fn main() {
-    ///     let dxl = Dynamixel {id: DynamixelID::ID(1)};
-    ///     let Packet::ProtocolOne(packet) = dxl.ping();
-    ///     assert_eq!(packet.generate().unwrap(), vec![0xFF, 0xFF, 0x01, 0x02, 0x01, 0xFB]);
+    ///     // Every byte of the Goal Position is written, even when it is 0
+    ///     let packet = Packet::write(1, 30, DataBytes::Two(0));
+    ///     assert_eq!(packet.generate().unwrap(), vec![0xFF, 0xFF, 0x01, 0x05, 0x03, 0x1E, 0x00, 0x00, 0xD8]);
     /// }
-    ///
     /// ```
-    fn ping(&mut self) -> Packet;
+    pub fn write(id: u8, address: u8, data: DataBytes) -> Packet {
+        let mut params = vec![address];
+        params.extend(Vec::<u8>::from(data));
 
-    /// Creates a packet to read from an address on the dynamixel, returning the crafted packet
+        Packet::new_raw(id, PacketType::Instruction(InstructionType::Write), params)
+    }
+
+    /// Creates a packet to register a value to write to an address on a
+    /// servo, which is written once the servo receives an action packet
     ///
-    /// This function implements section [4.2](https://emanual.robotis.com/docs/en/dxl/protocol1/#read)
     /// ```
-    /// use movement::dynamixel::{Dynamixel, Packet, DynamixelID, protocol_one::ProtocolOne};
+    /// use movement::dynamixel::{DataBytes, PacketManipulation};
+    /// use movement::dynamixel::protocol_one::Packet;
     ///
     /// fn main() {
-    ///     let dxl = Dynamixel {id: DynamixelID::ID(1)};
-    ///     let Packet::ProtocolOne(packet) = dxl.read(43, 1);
-    ///     assert_eq!(packet.generate().unwrap(), vec![0xFF, 0xFF, 0x01, 0x04, 0x02, 0x2B, 0x01, 0xCC]);
+    ///     let packet = Packet::register_write(1, 25, DataBytes::One(1));
+    ///     assert_eq!(packet.generate().unwrap(), vec![0xFF, 0xFF, 0x01, 0x04, 0x04, 0x19, 0x01, 0xDC]);
     /// }
-    ///
     /// ```
-    fn read(&mut self, address: u8, length: u64) -> Packet;
+    pub fn register_write(id: u8, address: u8, data: DataBytes) -> Packet {
+        let mut params = vec![address];
+        params.extend(Vec::<u8>::from(data));
+
+        Packet::new_raw(
+            id,
+            PacketType::Instruction(InstructionType::RegWrite),
+            params,
+        )
+    }
+
+    /// Creates a packet to action a registered value change on a servo
+    pub fn action(id: u8) -> Packet {
+        Packet::new_raw(id, PacketType::Instruction(InstructionType::Action), vec![])
+    }
+
+    /// Creates a packet to reset a servo to its factory settings
+    pub fn reset(id: u8) -> Packet {
+        Packet::new_raw(id, PacketType::Instruction(InstructionType::Reset), vec![])
+    }
+
+    /// Creates a packet to reboot a servo
+    pub fn reboot(id: u8) -> Packet {
+        Packet::new_raw(id, PacketType::Instruction(InstructionType::Reboot), vec![])
+    }
+}
+
+/// An incremental parser for status packets arriving from a serial port.
+/// Bytes may be pushed in arbitrarily sized chunks; the decoder hunts for
+/// packet headers, discarding any noise between packets, and only yields
+/// packets with a valid length and checksum.
+///
+/// Half-duplex adapters echo every transmitted byte back to the receiver,
+/// and in protocol 1 an echoed instruction packet is indistinguishable from
+/// a status packet. Any packet passed to [`PacketDecoder::expect_echo`] is
+/// therefore discarded if it is received before the next status packet.
+///
+/// ```
+/// use movement::dynamixel::protocol_one::PacketDecoder;
+///
+/// fn main() {
+///     let mut decoder = PacketDecoder::new();
+///     decoder.expect_echo(&[0xFF, 0xFF, 0x01, 0x02, 0x01, 0xFB]);
+///
+///     // Noise, then the echoed ping, then half of the response
+///     decoder.push(&[0x00, 0xFF, 0xFF, 0x01, 0x02, 0x01, 0xFB, 0xFF, 0xFF, 0x01]);
+///     assert!(decoder.next().is_none());
+///
+///     decoder.push(&[0x02, 0x00, 0xFC]);
+///     let packet = decoder.next().unwrap();
+///     assert_eq!(packet.id, 1);
+///     assert!(packet.parameters.is_empty());
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct PacketDecoder {
+    buffer: Vec<u8>,
+    echoes: VecDeque<Vec<u8>>,
+    checksum_errors: usize,
+}
+
+impl PacketDecoder {
+    /// Creates an empty decoder
+    pub fn new() -> PacketDecoder {
+        PacketDecoder::default()
+    }
+
+    /// Adds bytes read from the connection to the decoder
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend(bytes);
+    }
+
+    /// Registers a transmitted packet which may be echoed back by the adapter
+    pub fn expect_echo(&mut self, packet: &[u8]) {
+        self.echoes.push_back(packet.to_vec());
+    }
+
+    /// Discards all buffered data and expected echoes
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.echoes.clear();
+    }
+
+    /// The number of bytes received which have not yet been decoded
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// The number of complete packets which have been discarded as their
+    /// checksum was invalid
+    pub fn checksum_errors(&self) -> usize {
+        self.checksum_errors
+    }
+
+    /// Gets the length of the valid packet starting at `start`, or `None` if
+    /// the packet is incomplete
+    fn frame_length(&self, start: usize) -> Option<Result<usize, PacketReadError>> {
+        let frame = &self.buffer[start..];
+        if frame.len() < 4 {
+            return None;
+        }
+
+        // 0xFF is not a valid ID, and a packet has at least an error & checksum
+        let (id, length) = (frame[2], frame[3]);
+        if id == 0xFF || length < 2 {
+            return Some(Err(PacketReadError::InvalidHeader));
+        }
+
+        let total = length as usize + 4;
+        if frame.len() < total {
+            return None;
+        }
+
+        if frame[total - 1] == Packet::checksum(id, length, &frame[5..total - 1], frame[4]) {
+            Some(Ok(total))
+        } else {
+            Some(Err(PacketReadError::InvalidChecksum))
+        }
+    }
+
+    /// Finds the next complete packet in the buffer, returning its position
+    /// and length. Any bytes which cannot be part of a packet are discarded.
+    fn next_frame(&mut self) -> Option<(usize, usize)> {
+        loop {
+            let start = match self.buffer.windows(2).position(|w| w == [0xFF, 0xFF]) {
+                Some(start) => start,
+                None => {
+                    // Keep a trailing 0xFF, as it may be the start of a header
+                    let keep = (self.buffer.last() == Some(&0xFF)) as usize;
+                    self.buffer.drain(..self.buffer.len() - keep);
+                    return None;
+                }
+            };
+            self.buffer.drain(..start);
+
+            match self.frame_length(0) {
+                Some(Err(err)) => {
+                    if err == PacketReadError::InvalidChecksum {
+                        self.checksum_errors += 1;
+                    }
+                    self.buffer.remove(0);
+                }
+                Some(Ok(length)) => return Some((0, length)),
+                None => {
+                    // The header may have been noise, so look for a complete
+                    // packet further on rather than waiting on this one
+                    return (1..self.buffer.len().saturating_sub(1))
+                        .filter(|i| self.buffer[*i..*i + 2] == [0xFF, 0xFF])
+                        .find_map(|i| match self.frame_length(i) {
+                            Some(Ok(length)) => Some((i, length)),
+                            _ => None,
+                        });
+                }
+            }
+        }
+    }
+
+    /// Yields the bytes of the next complete packet of any type, or `None`
+    /// if more data is needed. Expected echoes are not filtered out.
+    pub fn next_raw(&mut self) -> Option<Vec<u8>> {
+        let (start, length) = self.next_frame()?;
+        Some(self.buffer.drain(..start + length).skip(start).collect())
+    }
+}
+
+impl Iterator for PacketDecoder {
+    type Item = Packet;
+
+    /// Yields the next complete status packet, or `None` if more data is
+    /// needed
+    fn next(&mut self) -> Option<Packet> {
+        while let Some(frame) = self.next_raw() {
+            // Echoes always arrive before the response, so any remaining
+            // expected echoes can be forgotten once a real packet arrives
+            if self.echoes.front() == Some(&frame) {
+                self.echoes.pop_front();
+                continue;
+            }
+            self.echoes.clear();
 
-    /// Creates a packet to write a value to the dynamixel at a given address,
-    /// returning the crafted packet
+            return Some(Packet::new_raw(
+                frame[2],
+                PacketType::Status(StatusType::get_error_types(&frame[4])),
+                frame[5..frame.len() - 1].to_vec(),
+            ));
+        }
+
+        None
+    }
+}
+
+/// This trait exposes all functionality possessed by Protocol One servos. For
+/// more information, please refer to <https://emanual.robotis.com/docs/en/dxl/protocol1/#instruction-details>
+///
+/// Every instruction is sent to the servo as soon as it is called. To craft
+/// an instruction packet without sending it, use the constructors on
+/// [`Packet`] instead.
+///
+/// Instructions which do not return data only wait for a status packet if
+/// the servo will send one, according to the `status_return_level` of the
+/// Dynamixel. Broadcast instructions never wait for a status packet.
+// TODO: Fix number sizes
+pub trait ProtocolOne {
+    /// Pings the dynamixel, returning the status packet sent in response
     ///
-    /// This function implements section [4.3](https://emanual.robotis.com/docs/en/dxl/protocol1/#write)
-    // TODO: Create doctest using working id() function
-    fn write(&mut self, address: u8, value: u64);
+    /// This function implements section [4.1](https://emanual.robotis.com/docs/en/dxl/protocol1/#ping)
+    fn ping(&mut self) -> Result<Packet, DynamixelError>;
 
-    /// Creates a packet to register a value to write to the dynamixel at a
-    /// given address, returning the crafted packet
+    /// Reads `length` bytes from an address on the dynamixel, returning the
+    /// status packet containing the data
     ///
-    /// This function implements section [4.4](https://emanual.robotis.com/docs/en/dxl/protocol1/#reg-write)
-    /// ```
-    /// use movement::dynamixel::{Dynamixel, Packet, DynamixelID, protocol_one::ProtocolOne};
+    /// This function implements section [4.2](https://emanual.robotis.com/docs/en/dxl/protocol1/#read)
+    fn read(&mut self, address: u8, length: u64) -> Result<Packet, DynamixelError>;
+
+    /// Writes a value to the dynamixel at a given address, waiting for the
+    /// servo to acknowledge the write
     ///
-    /// fn main() {
-    ///     let dxl = Dynamixel {id: DynamixelID::ID(1)};
-    ///     let Packet::ProtocolOne(packet) = dxl.register_write(30, 500);
-    ///     assert_eq!(packet.generate().unwrap(), vec![0xFF, 0xFF, 0x01, 0x05, 0x04, 0x1E, 0xF4, 0x01, 0xE2]);
-    /// }
+    /// This function implements section [4.3](https://emanual.robotis.com/docs/en/dxl/protocol1/#write)
+    fn write(&mut self, address: u8, data: DataBytes) -> Result<(), DynamixelError>;
+
+    /// Registers a value to write to the dynamixel at a given address, which
+    /// is only written once the servo receives an action instruction
     ///
-    /// ```
-    fn register_write(&self, address: u8, value: u64) -> super::Packet;
+    /// This function implements section [4.4](https://emanual.robotis.com/docs/en/dxl/protocol1/#reg-write)
+    fn register_write(&mut self, address: u8, data: DataBytes) -> Result<(), DynamixelError>;
 
-    /// Creates a packet to action the registered value change, returning the
-    /// crafted packet
+    /// Actions the value change registered by `register_write`
     ///
-    /// This function implements sction [4.5](https://emanual.robotis.com/docs/en/dxl/protocol1/#action)
-    // TODO: Create doctest using working id() function
-    fn action(&self) -> super::Packet;
+    /// This function implements section [4.5](https://emanual.robotis.com/docs/en/dxl/protocol1/#action)
+    fn action(&mut self) -> Result<(), DynamixelError>;
 
-    /// Creates a packet to reset the servo, returning the crafted packet
+    /// Resets the control table of the servo to its factory settings,
+    /// including setting its ID to 1
     ///
     /// This function implements section [4.6](https://emanual.robotis.com/docs/en/dxl/protocol1/#reset)
-    // TODO: Create doctest using working id() function
-    fn reset(&self) -> super::Packet;
+    fn reset(&mut self) -> Result<(), DynamixelError>;
 
-    /// Creates a packet to reboot the servo, returning the crafted packet
+    /// Reboots the servo
     ///
     /// This function implements section [4.7](https://emanual.robotis.com/docs/en/dxl/protocol1/#reboot)
-    fn reboot(&self) -> super::Packet;
+    fn reboot(&mut self) -> Result<(), DynamixelError>;
+
+    /// Reads the model number of the servo and loads the matching built-in
+    /// control table, returning the model number
+    fn detect_model(&mut self) -> Result<u16, DynamixelError>;
+
+    /// Reads an item from the servo by its name in the control table
+    fn read_item(&mut self, name: &str) -> Result<u64, DynamixelError>;
+
+    /// Reads an item from the servo by its name in the control table, sized
+    /// & signed according to the item
+    fn read_value(&mut self, name: &str) -> Result<DataBytes, DynamixelError>;
+
+    /// Writes a value to an item on the servo by its name in the control
+    /// table, using every byte of the item. See `Dynamixel::check_write` for
+    /// the values which are rejected.
+    fn write_item(&mut self, name: &str, value: u64) -> Result<(), DynamixelError>;
+
+    /// Detects the model of the servo, then reads its firmware version, baud
+    /// rate & status return level so that they are reported by
+    /// `DynamixelInformation`
+    ///
+    /// ```
+    /// use movement::dynamixel::control_table::builtin_model;
+    /// use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+    /// use movement::dynamixel::{Dynamixel, DynamixelID, DynamixelInformation};
+    /// use movement::dynamixel::protocol_one::ProtocolOne;
+    ///
+    /// fn main() {
+    ///     let mut bus = SimulatedBus::new();
+    ///     bus.add_servo(SimulatedDynamixel::new(3, builtin_model(29).unwrap()));
+    ///     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(3));
+    ///     dxl.read_information().unwrap();
+    ///
+    ///     assert_eq!(dxl.get_model_number(), Some(29));
+    ///     assert_eq!(dxl.get_baudrate(), Some(57_143));
+    /// }
+    /// ```
+    fn read_information(&mut self) -> Result<(), DynamixelError>;
 
     // fn bulk_read(&self) -> Result<Vec<Packet>, String>;
 }
 
+/// Checks that a status packet came from the expected servo and reports no
+/// errors, converting it into the appropriate `DynamixelError` otherwise
+fn check_status(packet: Packet, id: u8) -> Result<Packet, DynamixelError> {
+    if packet.id != id {
+        return Err(DynamixelError::IDMismatch {
+            expected: id,
+            found: packet.id,
+        });
+    }
+
+    match packet.packet_type {
+        PacketType::Status(ref errors) if !errors.is_empty() => {
+            Err(DynamixelError::Status(errors.clone()))
+        }
+        _ => Ok(packet),
+    }
+}
+
+/// The time a servo waits before responding, given its Return Delay Time
+fn return_delay(value: u64) -> Duration {
+    Duration::from_micros(2 * value)
+}
+
+/// Whether a servo sends a status packet in response to an instruction
+fn returns_status(level: StatusReturnLevel, instruction: InstructionType) -> bool {
+    match instruction {
+        InstructionType::Ping => true,
+        InstructionType::Read => level != StatusReturnLevel::PingOnly,
+        _ => level == StatusReturnLevel::All,
+    }
+}
+
+impl<C> super::Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Writes an instruction packet to the servo without waiting for a
+    /// response, marking it to be discarded if it is echoed back
+    fn send(&mut self, packet: &Packet) -> Result<(), DynamixelError> {
+        let sent = super::servo_connection::write_packet(self.connection_handler.as_mut(), packet)?;
+        self.decoder.expect_echo(&sent);
+        self.record_sent(&sent, packet);
+
+        Ok(())
+    }
+
+    /// Writes an instruction packet to the servo and waits for its status
+    /// packet, discarding any echo of the instruction. The instruction is
+    /// resent according to the retry policy. `None` is returned if the servo
+    /// will not respond to the instruction.
+    fn transmit(&mut self, packet: Packet) -> Result<Option<Packet>, DynamixelError> {
+        let responds = match packet.packet_type {
+            PacketType::Instruction(instruction) => {
+                returns_status(self.status_return_level, instruction)
+            }
+            PacketType::Status(_) => false,
+        };
+
+        let id = match self.get_id() {
+            DynamixelID::ID(id) if responds => id,
+            id => {
+                if let DynamixelID::ID(id) = id {
+                    self.statistics.record(id, |servo| servo.sent += 1);
+                }
+                self.send(&packet)?;
+                return Ok(None);
+            }
+        };
+
+        // A read is responded to with the data read, and any other
+        // instruction with an empty status packet
+        let response = match packet.packet_type {
+            PacketType::Instruction(InstructionType::Read) => 6 + packet.parameters[1] as usize,
+            _ => 6,
+        };
+        let timeout = self.response_timeout(packet.parameters.len() + 6, response);
+
+        let status = self.exchange_with_retries(id, |dxl| {
+            dxl.send(&packet)?;
+
+            let checksum_errors = dxl.decoder.checksum_errors();
+            let mut connection = RecordingReader::new(DeadlineReader::new(
+                dxl.connection_handler.as_mut(),
+                timeout,
+            ));
+            let mut status = read_packet(&mut connection, &mut dxl.decoder);
+            if let Err(DynamixelError::Timeout) = status {
+                if let Err(err) = drain(&mut connection, timeout) {
+                    status = Err(err);
+                }
+            }
+            let received = connection.into_bytes();
+            dxl.record_received(received, status.as_ref().ok());
+
+            // Corrupted packets are discarded by the decoder, so are only
+            // otherwise seen as a timeout
+            let corrupted = (dxl.decoder.checksum_errors() - checksum_errors) as u64;
+            dxl.statistics
+                .record(id, |servo| servo.checksum_errors += corrupted);
+
+            check_status(status?, id)
+        })?;
+
+        Ok(Some(status))
+    }
+
+    /// Transmits an instruction packet which must be responded to, such as a
+    /// read. A servo which is set not to respond is treated as timing out.
+    fn request(&mut self, packet: Packet) -> Result<Packet, DynamixelError> {
+        self.responding_id()?;
+        self.transmit(packet)?.ok_or(DynamixelError::Timeout)
+    }
+}
+
 // is it possible to turn this pattern into a macro?
 impl<C> ProtocolOne for super::Dynamixel<C>
 where
     C: Read + Write,
 {
-    fn ping(&mut self) -> Packet {
-        let dxl_id = self.get_id().into();
-        let packet = Packet::new(
-            dxl_id,
-            PacketType::Instruction(InstructionType::Ping),
-            vec![],
-        );
+    fn ping(&mut self) -> Result<Packet, DynamixelError> {
+        self.request(Packet::ping(self.get_id().into()))
+    }
 
-        super::servo_connection::write_packet(self.connection_handler.as_mut(), packet);
-        let raw_packet =
-            super::servo_connection::read_exact_packet(self.connection_handler.as_mut(), 6);
+    fn read(&mut self, address: u8, length: u64) -> Result<Packet, DynamixelError> {
+        let length = u8::try_from(length).map_err(|_| {
+            DynamixelError::InvalidPacket(format!("Cannot read {} bytes at once!", length))
+        })?;
 
-        Packet::from_vec(raw_packet, InstructionType::Ping, None).unwrap()
+        let status = self.request(Packet::read(self.get_id().into(), address, length))?;
+        if status.parameters.len() != length as usize {
+            return Err(DynamixelError::Packet(PacketReadError::InvalidLength));
+        }
+
+        Ok(status)
     }
 
-    fn read(&mut self, address: u8, length: u64) -> Packet {
-        let packet = Packet::new(
-            self.get_id().into(),
-            PacketType::Instruction(InstructionType::Read),
-            vec![address.into(), length],
-        );
+    fn write(&mut self, address: u8, data: DataBytes) -> Result<(), DynamixelError> {
+        self.transmit(Packet::write(self.get_id().into(), address, data))?;
+        Ok(())
+    }
 
-        super::servo_connection::write_packet(self.connection_handler.as_mut(), packet);
-        let raw_packet = super::servo_connection::read_exact_packet(
-            self.connection_handler.as_mut(),
-            6 + length as usize,
-        );
-        Packet::from_vec(raw_packet, InstructionType::Read, Some(length as usize)).unwrap()
+    fn register_write(&mut self, address: u8, data: DataBytes) -> Result<(), DynamixelError> {
+        self.transmit(Packet::register_write(self.get_id().into(), address, data))?;
+        Ok(())
     }
 
-    fn write(&mut self, address: u8, value: u64) {
-        let packet = Packet::new(
-            self.get_id().into(),
-            PacketType::Instruction(InstructionType::Write),
-            vec![address.into(), value],
-        );
+    fn action(&mut self) -> Result<(), DynamixelError> {
+        self.transmit(Packet::action(self.get_id().into()))?;
+        Ok(())
+    }
 
-        super::servo_connection::write_packet(self.connection_handler.as_mut(), packet);
+    fn reset(&mut self) -> Result<(), DynamixelError> {
+        self.transmit(Packet::reset(self.get_id().into()))?;
+        Ok(())
     }
 
-    fn register_write(&self, address: u8, value: u64) -> super::Packet {
-        super::Packet::ProtocolOne(Packet::new(
-            self.get_id().into(),
-            PacketType::Instruction(InstructionType::RegWrite),
-            vec![address.into(), value],
-        ))
+    fn reboot(&mut self) -> Result<(), DynamixelError> {
+        self.transmit(Packet::reboot(self.get_id().into()))?;
+        Ok(())
     }
 
-    fn action(&self) -> super::Packet {
-        super::Packet::ProtocolOne(Packet::new(
-            self.get_id().into(),
-            PacketType::Instruction(InstructionType::Action),
-            vec![],
-        ))
+    fn detect_model(&mut self) -> Result<u16, DynamixelError> {
+        let status = self.read(0, 2)?;
+        let number = LittleEndian::read_u16(&status.parameters);
+        let model = super::control_table::builtin_model(number)
+            .ok_or(DynamixelError::UnknownModel(number))?;
+
+        self.set_model(model);
+        Ok(number)
     }
 
-    fn reset(&self) -> super::Packet {
-        super::Packet::ProtocolOne(Packet::new(
-            self.get_id().into(),
-            PacketType::Instruction(InstructionType::Reset),
-            vec![],
-        ))
+    fn read_item(&mut self, name: &str) -> Result<u64, DynamixelError> {
+        let (address, size) = item_location(self.get_item(name)?)?;
+        let status = self.read(address, size as u64)?;
+
+        Ok(LittleEndian::read_uint(&status.parameters, size as usize))
     }
 
-    fn reboot(&self) -> super::Packet {
-        super::Packet::ProtocolOne(Packet::new(
-            self.get_id().into(),
-            PacketType::Instruction(InstructionType::Reboot),
-            vec![],
-        ))
+    fn read_value(&mut self, name: &str) -> Result<DataBytes, DynamixelError> {
+        let value = self.read_item(name)?;
+        self.item_bytes(name, value)
+    }
+
+    fn write_item(&mut self, name: &str, value: u64) -> Result<(), DynamixelError> {
+        let (address, _) = item_location(self.get_item(name)?)?;
+        let value = self.check_write(name, value)?;
+        let data = self.item_bytes(name, value)?;
+
+        self.write_item_with(name, value, |dxl| ProtocolOne::write(dxl, address, data))
+    }
+
+    fn read_information(&mut self) -> Result<(), DynamixelError> {
+        self.detect_model()?;
+        self.firmware_version = Some(self.read_item("Firmware Version")? as u8);
+        self.baud_rate = baud_rate(self.read_item("Baud Rate")? as u8);
+        self.status_return_level =
+            StatusReturnLevel::from(self.read_item("Status Return Level")? as u8);
+        self.return_delay = return_delay(self.read_item("Return Delay Time")?);
+
+        Ok(())
+    }
+}
+
+/// Converts a value of the Baud Rate item into bits per second, returning
+/// `None` if the value has no defined speed
+///
+/// ```
+/// use movement::dynamixel::protocol_one::baud_rate;
+///
+/// fn main() {
+///     assert_eq!(baud_rate(1), Some(1_000_000));
+///     assert_eq!(baud_rate(34), Some(57_143));
+///     assert_eq!(baud_rate(252), Some(3_000_000));
+/// }
+/// ```
+pub fn baud_rate(value: u8) -> Option<u32> {
+    match value {
+        0..=249 => Some((2_000_000.0 / (value as f64 + 1.0)).round() as u32),
+        // Only supported by the MX series
+        250 => Some(2_250_000),
+        251 => Some(2_500_000),
+        252 => Some(3_000_000),
+        _ => None,
+    }
+}
+
+/// Finds the value of the Baud Rate item for a speed in bits per second. As
+/// servos tolerate a mismatch of up to 3%, the closest value within 3% of the
+/// speed is used.
+///
+/// ```
+/// use movement::dynamixel::protocol_one::baud_rate_value;
+///
+/// fn main() {
+///     assert_eq!(baud_rate_value(1_000_000), Some(1));
+///     assert_eq!(baud_rate_value(57_600), Some(34));
+///     assert_eq!(baud_rate_value(1_500_000), None);
+/// }
+/// ```
+pub fn baud_rate_value(bits_per_second: u32) -> Option<u8> {
+    let error = |value: u8| match baud_rate(value) {
+        Some(rate) => (rate as f64 - bits_per_second as f64).abs() / bits_per_second as f64,
+        None => f64::INFINITY,
+    };
+
+    (0..=u8::MAX)
+        .min_by(|a, b| {
+            error(*a)
+                .partial_cmp(&error(*b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .filter(|value| error(*value) <= 0.03)
+}
+
+/// Gets the address & size of a control table item, which must fit within
+/// the 8-bit addresses used by protocol 1
+fn item_location(data: &super::ControlTableData) -> Result<(u8, u8), DynamixelError> {
+    match u8::try_from(data.address) {
+        Ok(address) => Ok((address, data.size)),
+        Err(_) => Err(DynamixelError::InvalidPacket(format!(
+            "Address {} is out of range for protocol 1!",
+            data.address
+        ))),
     }
 }
 
@@ -474,12 +1000,9 @@ where
 ///
 /// This function implements section [4.8](https://emanual.robotis.com/docs/en/dxl/protocol1/#sync-write)
 /// ```
-/// use movement::dynamixel::{Dynamixel, DynamixelID, Packet, SyncPacket};
-/// use movement::dynamixel::protocol_one::{ProtocolOne, sync_write};
+/// use movement::dynamixel::{Packet, PacketManipulation, SyncPacket};
+/// use movement::dynamixel::protocol_one::sync_write;
 /// fn main() {
-///     let dxl = Dynamixel {
-///         id: DynamixelID::Broadcast,
-///     };
 ///     let packets: Vec<SyncPacket> = vec![
 ///         SyncPacket {
 ///             id: 0,
@@ -503,7 +1026,7 @@ where
 ///         },
 ///     ];
 ///
-///     let Packet::ProtocolOne(packet) = sync_write(packets, 2).unwrap();
+///     let Packet::ProtocolOne(packet) = sync_write(packets, 2).unwrap() else { panic!() };
 ///     assert_eq!(
 ///         packet.generate().unwrap(),
 ///         vec![
@@ -574,15 +1097,11 @@ pub fn sync_write(
 ///
 /// This function implements section [4.9](https://emanual.robotis.com/docs/en/dxl/protocol1/#bulk-read)
 /// ```
-/// use movement::dynamixel::{Dynamixel, DynamixelID, Packet, BulkReadPacket};
-/// use movement::dynamixel::protocol_one::{ProtocolOne, bulk_read};
+/// use movement::dynamixel::{BulkReadPacket, Packet, PacketManipulation};
+/// use movement::dynamixel::protocol_one::bulk_read;
 /// fn main() {
-///     let dxl = Dynamixel {
-///         id: DynamixelID::Broadcast,
-///     };
-///
 ///     let packets: Vec<BulkReadPacket> = vec![BulkReadPacket{id: 1, length: 2, address: 30}, BulkReadPacket{id: 2, length: 2, address: 36}];
-///     let Packet::ProtocolOne(packet) = bulk_read(packets).unwrap();
+///     let Packet::ProtocolOne(packet) = bulk_read(packets).unwrap() else { panic!() };
 ///     assert_eq!(packet.generate().unwrap(), vec![0xFF, 0xFF, 0xFE, 0x09, 0x92, 0x00, 0x02, 0x01, 0x1E, 0x02, 0x02, 0x24, 0x1D]);
 /// }
 pub fn bulk_read(packets: Vec<super::BulkReadPacket>) -> Result<super::Packet, String> {
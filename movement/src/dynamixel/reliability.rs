@@ -0,0 +1,267 @@
+//! # Timeouts, retries & statistics
+//! A status packet can be lost or corrupted on a noisy bus, or may simply
+//! take longer to arrive than expected at a low baud rate. Each `Dynamixel`
+//! waits for a status packet for a time calculated from its baud rate &
+//! Return Delay Time, and resends the instruction according to its
+//! [`RetryPolicy`] if the status packet times out or is malformed.
+//!
+//! Every instruction sent & status packet received is counted per servo ID in
+//! its [`Statistics`], which are shared by every servo on a `DynamixelBus`
+//! and can be exported for diagnosing problems in the field.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID};
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(2));
+//!     dxl.retry_policy.retries = 2;
+//!
+//!     // Servo 2 does not exist, so every attempt times out
+//!     assert!(dxl.ping().is_err());
+//!
+//!     let statistics = dxl.statistics.get(2);
+//!     assert_eq!((statistics.sent, statistics.timeouts), (3, 3));
+//!     assert_eq!(statistics.retries, 2);
+//! }
+//! ```
+
+use super::protocol_one::PacketReadError;
+use super::{Dynamixel, DynamixelError};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// The baud rate assumed when the baud rate of the servo is not known
+pub(crate) const DEFAULT_BAUD_RATE: u32 = 57_600;
+
+/// The return delay assumed until the Return Delay Time of the servo is read
+pub(crate) const DEFAULT_RETURN_DELAY: Duration = Duration::from_micros(500);
+
+/// How long to wait for each status packet, and how often to resend an
+/// instruction which is not responded to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The number of times an instruction is resent after its status packet
+    /// times out or is malformed
+    pub retries: u32,
+    /// A fixed time to wait for each status packet, in place of the time
+    /// calculated from the baud rate & Return Delay Time
+    pub timeout: Option<Duration>,
+    /// The time added to the calculated timeout to allow for the latency of
+    /// the USB adapter
+    pub latency: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Waits for the calculated timeout, without retrying
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            retries: 0,
+            timeout: None,
+            latency: Duration::from_millis(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The time to wait for a status packet, from sending an instruction of
+    /// `request` bytes to receiving a status packet of `response` bytes
+    ///
+    /// ```
+    /// use movement::dynamixel::reliability::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// fn main() {
+    ///     let policy = RetryPolicy::default();
+    ///
+    ///     // 20 bytes take 200µs at 1Mbps
+    ///     let timeout = policy.timeout(1_000_000, Duration::from_micros(500), 8, 12);
+    ///     assert_eq!(timeout, Duration::from_micros(5700));
+    /// }
+    /// ```
+    pub fn timeout(
+        &self,
+        baud_rate: u32,
+        return_delay: Duration,
+        request: usize,
+        response: usize,
+    ) -> Duration {
+        if let Some(timeout) = self.timeout {
+            return timeout;
+        }
+
+        // Each byte is sent with a start & stop bit
+        let bits = 10 * (request + response) as u64;
+        let transfer = Duration::from_micros(bits * 1_000_000 / baud_rate.max(1) as u64);
+
+        transfer + return_delay + self.latency
+    }
+
+    /// Whether an instruction should be resent after failing with the error
+    pub fn should_retry(error: &DynamixelError) -> bool {
+        matches!(error, DynamixelError::Timeout | DynamixelError::Packet(_))
+    }
+}
+
+/// The number of packets exchanged with a single servo
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServoStatistics {
+    /// Instructions sent to the servo, including retries
+    pub sent: u64,
+    /// Status packets received from the servo
+    pub received: u64,
+    /// Instructions which were not responded to in time
+    pub timeouts: u64,
+    /// Status packets discarded as their checksum was invalid
+    pub checksum_errors: u64,
+    /// Status packets reporting an error on the servo
+    pub servo_errors: u64,
+    /// Instructions which were resent
+    pub retries: u64,
+}
+
+/// The statistics of every servo a connection has communicated with, which
+/// are shared between clones
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    servos: Arc<Mutex<BTreeMap<u8, ServoStatistics>>>,
+}
+
+impl Statistics {
+    /// Creates a set of statistics without any servos
+    pub fn new() -> Statistics {
+        Statistics::default()
+    }
+
+    /// The statistics of a single servo
+    pub fn get(&self, id: u8) -> ServoStatistics {
+        self.lock().get(&id).copied().unwrap_or_default()
+    }
+
+    /// The statistics of every servo, by ID
+    pub fn all(&self) -> BTreeMap<u8, ServoStatistics> {
+        self.lock().clone()
+    }
+
+    /// Clears the statistics of every servo
+    pub fn reset(&self) {
+        self.lock().clear();
+    }
+
+    /// Exports the statistics of every servo as CSV, with a row per servo
+    ///
+    /// ```
+    /// use movement::dynamixel::reliability::Statistics;
+    ///
+    /// fn main() {
+    ///     let statistics = Statistics::new();
+    ///     assert_eq!(
+    ///         statistics.to_csv(),
+    ///         "id,sent,received,timeouts,checksum_errors,servo_errors,retries\n"
+    ///     );
+    /// }
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("id,sent,received,timeouts,checksum_errors,servo_errors,retries\n");
+        for (id, servo) in self.lock().iter() {
+            // Writing to a string cannot fail
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                id,
+                servo.sent,
+                servo.received,
+                servo.timeouts,
+                servo.checksum_errors,
+                servo.servo_errors,
+                servo.retries
+            );
+        }
+
+        csv
+    }
+
+    /// Updates the statistics of a single servo
+    pub(crate) fn record<F>(&self, id: u8, update: F)
+    where
+        F: FnOnce(&mut ServoStatistics),
+    {
+        update(self.lock().entry(id).or_default());
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u8, ServoStatistics>> {
+        self.servos.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// The time to wait for a status packet of `response` bytes after
+    /// sending an instruction of `request` bytes
+    pub(crate) fn response_timeout(&self, request: usize, response: usize) -> Duration {
+        self.retry_policy.timeout(
+            self.baud_rate.unwrap_or(DEFAULT_BAUD_RATE),
+            self.return_delay,
+            request,
+            response,
+        )
+    }
+
+    /// Exchanges an instruction for a status packet with the servo,
+    /// resending the instruction according to the retry policy & recording
+    /// the outcome of each attempt
+    pub(crate) fn exchange_with_retries<F, T>(
+        &mut self,
+        id: u8,
+        mut exchange: F,
+    ) -> Result<T, DynamixelError>
+    where
+        F: FnMut(&mut Self) -> Result<T, DynamixelError>,
+    {
+        let mut attempt = 0;
+        loop {
+            // Anything left over from an earlier attempt is not a response
+            // to this one
+            self.decoder.clear();
+            self.decoder_two.clear();
+
+            self.statistics.record(id, |servo| servo.sent += 1);
+            let result = exchange(self);
+
+            self.statistics.record(id, |servo| match &result {
+                Ok(_) => servo.received += 1,
+                Err(DynamixelError::Timeout) => servo.timeouts += 1,
+                Err(DynamixelError::Packet(PacketReadError::InvalidChecksum)) => {
+                    servo.checksum_errors += 1
+                }
+                Err(DynamixelError::Packet(_)) => servo.received += 1,
+                Err(DynamixelError::Status(_)) | Err(DynamixelError::ProtocolTwoStatus { .. }) => {
+                    servo.received += 1;
+                    servo.servo_errors += 1;
+                }
+                Err(_) => {}
+            });
+
+            match result {
+                Err(ref err)
+                    if attempt < self.retry_policy.retries && RetryPolicy::should_retry(err) =>
+                {
+                    attempt += 1;
+                    self.statistics.record(id, |servo| servo.retries += 1);
+                }
+                result => return result,
+            }
+        }
+    }
+}
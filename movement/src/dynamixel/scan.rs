@@ -0,0 +1,188 @@
+//! # Bus discovery
+//! Servos arrive from the factory with an ID of 1 and a baud rate which
+//! differs between series, and may be left at an unknown ID or baud rate
+//! after being configured elsewhere. This file contains a scan which pings
+//! every ID at each baud rate, for each protocol, reporting any servos which
+//! respond. As a full scan can take some time, progress is reported after
+//! every ping so that it can be shown to the user.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::scan::{scan, ScanOptions};
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::Protocol;
+//!
+//! fn main() {
+//!     // The MX-28 defaults to 57600bps
+//!     let mut port = SimulatedBus::new();
+//!     port.add_servo(SimulatedDynamixel::new(7, builtin_model(29).unwrap()));
+//!
+//!     let options = ScanOptions {
+//!         ids: 0..=10,
+//!         baud_rates: vec![1_000_000, 57_600],
+//!         protocols: vec![Protocol::One],
+//!     };
+//!     let mut pinged = 0;
+//!     let found = scan(&mut port, &options, |progress| pinged = progress.completed).unwrap();
+//!
+//!     assert_eq!(pinged, 22);
+//!     assert_eq!(found.len(), 1);
+//!     assert_eq!((found[0].id, found[0].baud_rate), (7, 57_600));
+//!     assert_eq!(found[0].model_number, Some(29));
+//! }
+//! ```
+
+use super::protocol_one::ProtocolOne;
+use super::protocol_two::ProtocolTwo;
+use super::servo_connection::BaudRate;
+use super::{Dynamixel, DynamixelError, DynamixelID, Protocol};
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+
+/// The baud rates supported by Dynamixels, with the factory defaults first
+pub const BAUD_RATES: [u32; 8] = [
+    1_000_000, 57_600, 115_200, 9_600, 2_000_000, 3_000_000, 4_000_000, 4_500_000,
+];
+
+/// The IDs, baud rates & protocols to search
+#[derive(Clone, Debug)]
+pub struct ScanOptions {
+    pub ids: RangeInclusive<u8>,
+    pub baud_rates: Vec<u32>,
+    pub protocols: Vec<Protocol>,
+}
+
+impl Default for ScanOptions {
+    /// Searches every ID at every standard baud rate, using both protocols
+    fn default() -> ScanOptions {
+        ScanOptions {
+            ids: 0..=253,
+            baud_rates: BAUD_RATES.to_vec(),
+            protocols: vec![Protocol::One, Protocol::Two],
+        }
+    }
+}
+
+/// A servo which responded during a scan. The model number & firmware
+/// version are `None` if the servo responded with an error, such as when it
+/// is overheating.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoundServo {
+    pub id: u8,
+    pub baud_rate: u32,
+    pub protocol: Protocol,
+    pub model_number: Option<u16>,
+    pub firmware_version: Option<u8>,
+}
+
+/// The state of a scan after an ID has been pinged
+#[derive(Clone, Copy, Debug)]
+pub struct ScanProgress {
+    pub protocol: Protocol,
+    pub baud_rate: u32,
+    pub id: u8,
+    pub completed: usize,
+    pub total: usize,
+    pub found: usize,
+}
+
+/// Pings every combination of ID, baud rate & protocol in the options,
+/// returning each servo that responds. `progress` is called after every ping.
+///
+/// Missing or corrupted responses are expected while scanning, so only a
+/// failure of the connection itself will stop the scan. The connection is
+/// left at the last baud rate scanned.
+pub fn scan<C, F>(
+    connection: &mut C,
+    options: &ScanOptions,
+    mut progress: F,
+) -> Result<Vec<FoundServo>, DynamixelError>
+where
+    C: Read + Write + BaudRate,
+    F: FnMut(&ScanProgress),
+{
+    let total = options.ids.clone().count() * options.baud_rates.len() * options.protocols.len();
+    let mut found = vec![];
+    let mut completed = 0;
+
+    for &protocol in options.protocols.iter() {
+        for &baud_rate in options.baud_rates.iter() {
+            connection.set_baud_rate(baud_rate)?;
+
+            for id in options.ids.clone() {
+                if let Some((model_number, firmware_version)) =
+                    probe(connection, id, baud_rate, protocol)?
+                {
+                    found.push(FoundServo {
+                        id,
+                        baud_rate,
+                        protocol,
+                        model_number,
+                        firmware_version,
+                    });
+                }
+
+                completed += 1;
+                progress(&ScanProgress {
+                    protocol,
+                    baud_rate,
+                    id,
+                    completed,
+                    total,
+                    found: found.len(),
+                });
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// The model number & firmware version of a servo, if they could be read
+type Identity = (Option<u16>, Option<u8>);
+
+/// Pings a single ID, returning the identity of the servo if it responded
+fn probe<C>(
+    connection: &mut C,
+    id: u8,
+    baud_rate: u32,
+    protocol: Protocol,
+) -> Result<Option<Identity>, DynamixelError>
+where
+    C: Read + Write,
+{
+    // The time waited for a response depends on the baud rate
+    let mut dxl = Dynamixel::new_empty(connection, DynamixelID::ID(id));
+    dxl.baud_rate = Some(baud_rate);
+
+    match protocol {
+        Protocol::One => {
+            match ProtocolOne::ping(&mut dxl) {
+                Ok(_) | Err(DynamixelError::Status(_)) => {}
+                Err(DynamixelError::Io(err)) => return Err(DynamixelError::Io(err)),
+                Err(_) => return Ok(None),
+            }
+
+            // The model number & firmware version are stored consecutively
+            match ProtocolOne::read(&mut dxl, 0, 3) {
+                Ok(status) => Ok(Some((
+                    Some(LittleEndian::read_u16(&status.parameters[0..2])),
+                    Some(status.parameters[2]),
+                ))),
+                Err(DynamixelError::Io(err)) => Err(DynamixelError::Io(err)),
+                Err(_) => Ok(Some((None, None))),
+            }
+        }
+        Protocol::Two => match ProtocolTwo::ping(&mut dxl) {
+            // Protocol 2 servos report their identity in response to a ping
+            Ok(status) if status.parameters.len() == 3 => Ok(Some((
+                Some(LittleEndian::read_u16(&status.parameters[0..2])),
+                Some(status.parameters[2]),
+            ))),
+            Ok(_) | Err(DynamixelError::ProtocolTwoStatus { .. }) => Ok(Some((None, None))),
+            Err(DynamixelError::Io(err)) => Err(DynamixelError::Io(err)),
+            Err(_) => Ok(None),
+        },
+    }
+}
@@ -1,25 +1,133 @@
-use super::PacketManipulation;
-use std::io::{Read, Write};
+use super::protocol_one::{Packet, PacketDecoder};
+use super::{DynamixelError, PacketManipulation};
+use serialport::SerialPort;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
 
-pub fn write_packet<W, P>(connection: &mut W, packet: P)
+/// A connection whose baud rate can be changed, such as a serial port
+pub trait BaudRate {
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), DynamixelError>;
+}
+
+impl BaudRate for Box<dyn SerialPort> {
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), DynamixelError> {
+        SerialPort::set_baud_rate(self.as_mut(), baud_rate)
+            .map_err(|err| DynamixelError::Io(err.into()))
+    }
+}
+
+impl<T: BaudRate + ?Sized> BaudRate for &mut T {
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), DynamixelError> {
+        (**self).set_baud_rate(baud_rate)
+    }
+}
+
+/// A reader which keeps waiting for data each time the connection times
+/// out, until the deadline has passed. This allows the time waited for a
+/// status packet to differ from the timeout of the serial port itself.
+pub struct DeadlineReader<'a, R: ?Sized> {
+    connection: &'a mut R,
+    deadline: Instant,
+}
+
+impl<'a, R: Read + ?Sized> DeadlineReader<'a, R> {
+    /// Creates a reader which waits for up to `timeout` from now
+    pub fn new(connection: &'a mut R, timeout: Duration) -> Self {
+        DeadlineReader {
+            connection,
+            deadline: Instant::now() + timeout,
+        }
+    }
+}
+
+impl<'a, R: Read + ?Sized> Read for DeadlineReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.connection.read(buf) {
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                    ) && Instant::now() < self.deadline => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Writes a packet to the connection, returning the bytes that were sent
+pub fn write_packet<W, P>(connection: &mut W, packet: P) -> Result<Vec<u8>, DynamixelError>
 where
     W: Write,
     P: PacketManipulation,
 {
-    let pck = packet.generate().unwrap();
-    connection.write(&pck).unwrap();
+    let pck = packet.generate().map_err(DynamixelError::InvalidPacket)?;
+    connection.write_all(&pck)?;
+
+    Ok(pck)
+}
+
+/// Reads from the connection until the decoder yields a complete status
+/// packet. Any data received after the packet is kept in the decoder for the
+/// next call.
+pub fn read_packet<R: Read>(
+    connection: &mut R,
+    decoder: &mut PacketDecoder,
+) -> Result<Packet, DynamixelError> {
+    let mut buf = [0u8; 64];
+
+    loop {
+        if let Some(packet) = decoder.next() {
+            return Ok(packet);
+        }
+
+        match connection.read(&mut buf)? {
+            0 => return Err(DynamixelError::Timeout),
+            n => decoder.push(&buf[..n]),
+        }
+    }
 }
 
-pub fn read_packet<R: Read>(connection: &mut R) -> Vec<u8> {
-    let mut buf: Vec<u8> = vec![];
-    connection.read(&mut buf).unwrap();
+/// Reads & discards anything received until the connection has been quiet
+/// for `timeout`, returning the discarded bytes. This is used after a status
+/// packet fails to arrive in time, so that it is not mistaken for the
+/// response to the next instruction if it arrives late.
+pub fn drain<R: Read + ?Sized>(
+    connection: &mut R,
+    timeout: Duration,
+) -> Result<Vec<u8>, DynamixelError> {
+    let mut buf = [0u8; 64];
+    let mut drained = vec![];
+    let mut deadline = Instant::now() + timeout;
 
-    buf
+    loop {
+        match connection.read(&mut buf) {
+            Ok(0) => return Ok(drained),
+            Ok(n) => {
+                drained.extend(&buf[..n]);
+                deadline = Instant::now() + timeout;
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                if Instant::now() >= deadline {
+                    return Ok(drained);
+                }
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
 }
 
-pub fn read_exact_packet<R: Read>(connection: &mut R, len: usize) -> Vec<u8> {
+pub fn read_exact_packet<R: Read>(
+    connection: &mut R,
+    len: usize,
+) -> Result<Vec<u8>, DynamixelError> {
     let mut buf: Vec<u8> = vec![0; len];
-    connection.read_exact(&mut buf).unwrap();
+    connection.read_exact(&mut buf)?;
 
-    buf
+    Ok(buf)
 }
@@ -0,0 +1,601 @@
+//! # Simulated Dynamixels
+//! This file contains a software model of a chain of protocol 1 Dynamixels,
+//! which can be used in place of a serial port to test robot code without
+//! any hardware attached. Each simulated servo keeps a real control table in
+//! memory, responds with correctly formed status packets (honouring the
+//! Return Delay Time & Status Return Level items) and moves its present
+//! position toward its goal position over time.
+//!
+//! Faults can be injected to test how robot code copes with a real bus:
+//! status packets can be dropped or delayed, and written bytes echoed back as
+//! by a half-duplex adapter. Like a serial port, reads time out if no status
+//! packet arrives within the `timeout` of the bus.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID};
+//! use std::time::Duration;
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!     dxl.detect_model().unwrap();
+//!     dxl.write_item("LED", 1).unwrap();
+//!
+//!     // Servos on the bus can also be manipulated directly
+//!     let bus = &mut dxl.connection_handler;
+//!     assert_eq!(bus.servo(1).unwrap().get_item("LED"), Some(1));
+//!     bus.servo_mut(1).unwrap().set_item("Goal Position", 612);
+//!
+//!     // At full speed the AX-12A moves 100 steps in well under a second
+//!     bus.advance(Duration::from_secs(1));
+//!     assert_eq!(dxl.read_item("Present Position").unwrap(), 612);
+//! }
+//! ```
+
+use super::control_table::Model;
+use super::protocol_one::{
+    baud_rate as baud_rate_of, InstructionType, Packet, PacketDecoder, PacketType, StatusType,
+};
+use super::servo_connection::BaudRate;
+use super::{AccessLevel, DynamixelError, DynamixelID};
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+
+/// The time a read waits for data by default, matching the serial ports
+/// opened by `connection::usb`
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(5);
+
+/// The speed of an AX/MX series servo when its Moving Speed is set to 0
+const MAX_RPM: f64 = 114.0;
+
+/// The speed represented by a single unit of Moving Speed
+const RPM_PER_UNIT: f64 = 0.111;
+
+/// A single simulated servo, storing its control table in memory
+pub struct SimulatedDynamixel {
+    pub model: Model,
+    memory: Vec<u8>,
+    defaults: Vec<u8>,
+    registered: Option<(u16, Vec<u8>)>,
+    position: f64,
+    last_update: Duration,
+}
+
+impl SimulatedDynamixel {
+    /// Creates a servo with the given ID, with its control table populated
+    /// with the initial values of the model
+    pub fn new(id: u8, model: Model) -> SimulatedDynamixel {
+        let size = model
+            .items
+            .iter()
+            .map(|item| item.data.address as usize + item.data.size as usize)
+            .max()
+            .unwrap_or(0);
+
+        let mut servo = SimulatedDynamixel {
+            model,
+            memory: vec![0; size],
+            defaults: vec![],
+            registered: None,
+            position: 0.0,
+            last_update: Duration::from_secs(0),
+        };
+
+        let initial_values: Vec<(String, i64)> = servo
+            .model
+            .items
+            .iter()
+            .filter_map(|item| {
+                let value = item.data.initial_value.as_ref()?.parse().ok()?;
+                Some((item.name.clone(), value))
+            })
+            .collect();
+        for (name, value) in initial_values {
+            servo.set_item(&name, value);
+        }
+
+        // Start in the centre of the servo's range, holding position
+        let centre = match servo.model.item("Goal Position").and_then(|i| i.data.range) {
+            Some((min, max)) => (min + max + 1) / 2,
+            None => 0,
+        };
+        let max_torque = servo.get_item("Max Torque").unwrap_or(0);
+
+        servo.set_item("Model Number", servo.model.number.into());
+        servo.set_item("Goal Position", centre);
+        servo.set_item("Present Position", centre);
+        servo.set_item("Torque Limit", max_torque);
+        servo.set_item("Present Voltage", 120);
+        servo.set_item("Present Temperature", 32);
+        servo.position = centre as f64;
+        servo.defaults = servo.memory.clone();
+        servo.set_item("ID", id.into());
+
+        servo
+    }
+
+    /// The current ID of the servo
+    pub fn id(&self) -> u8 {
+        self.get_item("ID").unwrap_or(0) as u8
+    }
+
+    /// Reads an unsigned item from the control table by name
+    pub fn get_item(&self, name: &str) -> Option<i64> {
+        let data = &self.model.item(name)?.data;
+        let start = data.address as usize;
+        let bytes = self.memory.get(start..start + data.size as usize)?;
+
+        Some(LittleEndian::read_uint(bytes, bytes.len()) as i64)
+    }
+
+    /// Writes an item to the control table by name, ignoring its access
+    /// level. This is useful to simulate sensor readings, such as a servo
+    /// overheating. Returns `None` if the item does not exist.
+    pub fn set_item(&mut self, name: &str, value: i64) -> Option<()> {
+        let data = &self.model.item(name)?.data;
+        let start = data.address as usize;
+        let bytes = self.memory.get_mut(start..start + data.size as usize)?;
+        let size = bytes.len();
+        LittleEndian::write_uint(bytes, value as u64 & (u64::MAX >> (64 - 8 * size)), size);
+
+        if name == "Present Position" {
+            self.position = value as f64;
+        }
+
+        Some(())
+    }
+
+    /// The number of status packets the servo sends, where 0 only responds to
+    /// pings, 1 also responds to reads and 2 responds to everything
+    fn status_return_level(&self) -> i64 {
+        self.get_item("Status Return Level").unwrap_or(2)
+    }
+
+    /// The time the servo waits before sending a status packet
+    fn return_delay(&self) -> Duration {
+        Duration::from_micros(2 * self.get_item("Return Delay Time").unwrap_or(0) as u64)
+    }
+
+    /// Writes raw data to the control table, as instructed by a packet.
+    /// Writes to read-only items or with out-of-range values are rejected.
+    fn write_memory(&mut self, address: u16, data: &[u8]) -> Result<(), StatusType> {
+        let start = address as usize;
+        let end = start + data.len();
+        if data.is_empty() || end > self.memory.len() {
+            return Err(StatusType::Range);
+        }
+
+        for item in self.model.items.iter() {
+            let item_start = item.data.address as usize;
+            let item_end = item_start + item.data.size as usize;
+            if item_start >= end || start >= item_end {
+                continue;
+            }
+
+            if item.data.access == AccessLevel::Read {
+                return Err(StatusType::Range);
+            }
+
+            // Only check the range when the whole item is being written
+            if let (true, Some((min, max))) =
+                (start <= item_start && item_end <= end, item.data.range)
+            {
+                let bytes = &data[item_start - start..item_end - start];
+                let value = LittleEndian::read_uint(bytes, bytes.len()) as i64;
+                if value < min || value > max {
+                    return Err(StatusType::Range);
+                }
+            }
+        }
+
+        self.memory[start..end].copy_from_slice(data);
+        if start < self.present_position_end() && end > self.present_position_start() {
+            self.position = self.get_item("Present Position").unwrap_or(0) as f64;
+        }
+
+        Ok(())
+    }
+
+    fn present_position_start(&self) -> usize {
+        self.model
+            .item("Present Position")
+            .map(|item| item.data.address as usize)
+            .unwrap_or(0)
+    }
+
+    fn present_position_end(&self) -> usize {
+        self.model
+            .item("Present Position")
+            .map(|item| item.data.address as usize + item.data.size as usize)
+            .unwrap_or(0)
+    }
+
+    /// Moves the servo toward its goal position (or spins it, in wheel mode)
+    /// based on the time passed since the last update
+    fn update(&mut self, now: Duration) {
+        let elapsed = now.checked_sub(self.last_update).unwrap_or_default();
+        self.last_update = now;
+
+        let (goal, speed, max) = match (
+            self.get_item("Goal Position"),
+            self.get_item("Moving Speed"),
+            self.model.item("Goal Position").and_then(|i| i.data.range),
+        ) {
+            (Some(goal), Some(speed), Some((_, max))) => (goal, speed, max),
+            _ => return,
+        };
+
+        let wheel = self.get_item("CW Angle Limit") == Some(0)
+            && self.get_item("CCW Angle Limit") == Some(0);
+        let magnitude = speed & 0x3FF;
+        let rpm = match (magnitude, wheel) {
+            (0, true) => 0.0,
+            (0, false) => MAX_RPM,
+            (magnitude, _) => magnitude as f64 * RPM_PER_UNIT,
+        };
+
+        // AX series servos cover 300 degrees, while MX series cover 360
+        let degrees = if max > 1023 { 360.0 } else { 300.0 };
+        let step = rpm * 6.0 * (max + 1) as f64 / degrees * elapsed.as_secs_f64();
+
+        let (present_speed, moving) = if wheel {
+            let direction = if speed & 0x400 != 0 { -1.0 } else { 1.0 };
+            self.position = (self.position + direction * step).rem_euclid((max + 1) as f64);
+            (speed, magnitude != 0)
+        } else {
+            let difference = goal as f64 - self.position;
+            if difference.abs() <= step {
+                self.position = goal as f64;
+                (0, false)
+            } else {
+                self.position += difference.signum() * step;
+                let direction = if difference < 0.0 { 0x400 } else { 0 };
+                ((rpm / RPM_PER_UNIT) as i64 | direction, true)
+            }
+        };
+
+        let position = self.position.round() as i64;
+        self.set_item("Present Position", position);
+        self.set_item("Present Speed", present_speed);
+        self.set_item("Moving", moving as i64);
+    }
+
+    /// Carries out an instruction addressed to this servo, returning the
+    /// parameters of the status packet or the error to report
+    fn execute(
+        &mut self,
+        instruction: InstructionType,
+        params: &[u8],
+    ) -> Result<Vec<u8>, StatusType> {
+        match instruction {
+            InstructionType::Ping | InstructionType::Reboot => Ok(vec![]),
+            InstructionType::Read => {
+                if params.len() != 2 {
+                    return Err(StatusType::Instruction);
+                }
+
+                let start = params[0] as usize;
+                match self.memory.get(start..start + params[1] as usize) {
+                    Some(data) => Ok(data.to_vec()),
+                    None => Err(StatusType::Range),
+                }
+            }
+            InstructionType::Write => match params.split_first() {
+                Some((address, data)) => self.write_memory(*address as u16, data).map(|_| vec![]),
+                None => Err(StatusType::Instruction),
+            },
+            InstructionType::RegWrite => match params.split_first() {
+                Some((address, data)) => {
+                    self.registered = Some((*address as u16, data.to_vec()));
+                    self.set_item("Registered", 1);
+                    Ok(vec![])
+                }
+                None => Err(StatusType::Instruction),
+            },
+            InstructionType::Action => match self.registered.take() {
+                Some((address, data)) => {
+                    self.set_item("Registered", 0);
+                    self.write_memory(address, &data).map(|_| vec![])
+                }
+                None => Err(StatusType::Instruction),
+            },
+            InstructionType::Reset => {
+                self.memory = self.defaults.clone();
+                self.position = self.get_item("Present Position").unwrap_or(0) as f64;
+                self.set_item("ID", 1);
+                Ok(vec![])
+            }
+            InstructionType::SyncWrite | InstructionType::BulkRead => Err(StatusType::Instruction),
+        }
+    }
+}
+
+/// A chain of simulated servos sharing a single connection. Instruction
+/// packets written to the bus are carried out by the addressed servos, and
+/// their status packets can then be read back.
+pub struct SimulatedBus {
+    servos: Vec<SimulatedDynamixel>,
+    decoder: PacketDecoder,
+    output: VecDeque<(Duration, Vec<u8>)>,
+    echo: bool,
+    baud_rate: Option<u32>,
+    timeout: Duration,
+    delay: Duration,
+    dropped: usize,
+    start: Instant,
+    skipped: Duration,
+}
+
+impl Default for SimulatedBus {
+    fn default() -> SimulatedBus {
+        SimulatedBus::new()
+    }
+}
+
+impl SimulatedBus {
+    /// Creates a bus without any servos attached
+    pub fn new() -> SimulatedBus {
+        SimulatedBus {
+            servos: vec![],
+            decoder: PacketDecoder::new(),
+            output: VecDeque::new(),
+            echo: false,
+            baud_rate: None,
+            timeout: DEFAULT_TIMEOUT,
+            delay: Duration::from_secs(0),
+            dropped: 0,
+            start: Instant::now(),
+            skipped: Duration::from_secs(0),
+        }
+    }
+
+    /// Attaches a servo to the bus
+    pub fn add_servo(&mut self, servo: SimulatedDynamixel) {
+        self.servos.push(servo);
+    }
+
+    /// Finds an attached servo by its current ID
+    pub fn servo(&self, id: u8) -> Option<&SimulatedDynamixel> {
+        self.servos.iter().find(|servo| servo.id() == id)
+    }
+
+    /// Finds an attached servo by its current ID
+    pub fn servo_mut(&mut self, id: u8) -> Option<&mut SimulatedDynamixel> {
+        self.servos.iter_mut().find(|servo| servo.id() == id)
+    }
+
+    /// Sets whether written bytes are echoed back, as with a half-duplex
+    /// adapter
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Sets how long a read waits for a status packet before timing out, as
+    /// with the timeout of a serial port
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Delays every status packet by `delay` on top of the servo's return
+    /// delay, as if the bus or adapter were slow
+    pub fn delay_replies(&mut self, delay: Duration) {
+        self.delay = delay;
+    }
+
+    /// Discards the next `count` status packets, as if they were lost on the
+    /// bus. The servos still carry out the instructions.
+    pub fn drop_replies(&mut self, count: usize) {
+        self.dropped = count;
+    }
+
+    /// Moves the simulation forward in time without waiting
+    pub fn advance(&mut self, duration: Duration) {
+        self.skipped += duration;
+    }
+
+    /// The time passed since the simulation was created
+    fn now(&self) -> Duration {
+        self.start.elapsed() + self.skipped
+    }
+
+    /// Whether a servo can communicate at the current baud rate of the bus.
+    /// As with real hardware, a small mismatch between baud rates is tolerated.
+    fn hears(&self, servo: &SimulatedDynamixel) -> bool {
+        let baud_rate = match self.baud_rate {
+            Some(baud_rate) => baud_rate as f64,
+            None => return true,
+        };
+
+        match servo
+            .get_item("Baud Rate")
+            .and_then(|value| baud_rate_of(value as u8))
+        {
+            Some(servo_rate) => (servo_rate as f64 - baud_rate).abs() / baud_rate <= 0.03,
+            None => false,
+        }
+    }
+
+    /// Finds the index of the servo with the given ID, if it can hear the bus
+    fn find(&self, id: u8) -> Option<usize> {
+        self.servos
+            .iter()
+            .position(|servo| servo.id() == id && self.hears(servo))
+    }
+
+    /// Queues a status packet to be sent once the servo's return delay passes,
+    /// returning the time at which it is sent. A dropped status packet still
+    /// takes up its time on the bus.
+    fn respond(
+        &mut self,
+        index: usize,
+        after: Duration,
+        result: Result<Vec<u8>, StatusType>,
+    ) -> Duration {
+        let servo = &self.servos[index];
+        let (errors, params) = match result {
+            Ok(params) => (vec![], params),
+            Err(error) => (vec![error], vec![]),
+        };
+        let packet = Packet::new_raw(servo.id(), PacketType::Status(errors), params);
+        let ready = after + servo.return_delay() + self.delay;
+
+        if self.dropped > 0 {
+            self.dropped -= 1;
+        } else {
+            self.output.push_back((ready, packet.to_bytes()));
+        }
+        ready
+    }
+
+    /// Carries out a complete instruction packet received by the bus
+    fn process(&mut self, frame: Vec<u8>) {
+        let now = self.now();
+        for servo in self.servos.iter_mut() {
+            servo.update(now);
+        }
+
+        let id = frame[2];
+        let params = &frame[5..frame.len() - 1];
+        let broadcast = id == u8::from(DynamixelID::Broadcast);
+        let instruction = match InstructionType::try_from(frame[4]) {
+            Ok(instruction) => instruction,
+            Err(_) => {
+                if let Some(index) = self.find(id) {
+                    self.respond(index, now, Err(StatusType::Instruction));
+                }
+                return;
+            }
+        };
+
+        match instruction {
+            InstructionType::SyncWrite => {
+                if params.len() < 2 || params[1] == 0 {
+                    return;
+                }
+
+                let (address, length) = (params[0] as u16, params[1] as usize);
+                for chunk in params[2..].chunks(length + 1) {
+                    if let Some(index) = self.find(chunk[0]) {
+                        let _ = self.servos[index].write_memory(address, &chunk[1..]);
+                    }
+                }
+            }
+            InstructionType::BulkRead => {
+                // Each servo responds after the servo listed before it
+                let mut after = now;
+                for chunk in params.get(1..).unwrap_or(&[]).chunks(3) {
+                    if chunk.len() != 3 {
+                        break;
+                    }
+
+                    if let Some(index) = self.find(chunk[1]) {
+                        let result = self.servos[index]
+                            .execute(InstructionType::Read, &[chunk[2], chunk[0]]);
+                        after = self.respond(index, after, result);
+                    }
+                }
+            }
+            _ if broadcast => {
+                for index in 0..self.servos.len() {
+                    if self.hears(&self.servos[index]) {
+                        let _ = self.servos[index].execute(instruction, params);
+                    }
+                }
+            }
+            _ => {
+                let index = match self.find(id) {
+                    Some(index) => index,
+                    None => return,
+                };
+
+                let result = self.servos[index].execute(instruction, params);
+                let responds = match (instruction, self.servos[index].status_return_level()) {
+                    (InstructionType::Ping, _) => true,
+                    (InstructionType::Read, level) => level >= 1,
+                    (_, level) => level >= 2,
+                };
+
+                if responds {
+                    self.respond(index, now, result);
+                }
+            }
+        }
+    }
+}
+
+impl Write for SimulatedBus {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.echo {
+            let now = self.now();
+            self.output.push_back((now, buf.to_vec()));
+        }
+
+        self.decoder.push(buf);
+        while let Some(frame) = self.decoder.next_raw() {
+            self.process(frame);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl BaudRate for SimulatedBus {
+    /// Sets the baud rate of the bus, after which only servos with a matching
+    /// Baud Rate item will respond. Until a baud rate is set, all servos
+    /// respond.
+    fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), DynamixelError> {
+        self.baud_rate = Some(baud_rate);
+        Ok(())
+    }
+}
+
+impl Read for SimulatedBus {
+    /// Reads any status packets sent by the servos, waiting for each servo's
+    /// return delay to pass. A read times out if the next status packet is
+    /// not sent within the `timeout` of the bus. As servos respond as soon as
+    /// an instruction is written, the end of the stream is reported if no
+    /// servo is going to respond.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let ready = match self.output.front() {
+            Some((ready, _)) => *ready,
+            None => return Ok(0),
+        };
+
+        let now = self.now();
+        if ready > now + self.timeout {
+            std::thread::sleep(self.timeout);
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no status packet was sent in time",
+            ));
+        }
+        if ready > now {
+            std::thread::sleep(ready - now);
+        }
+
+        let mut length = 0;
+        if let Some((_, bytes)) = self.output.front_mut() {
+            length = buf.len().min(bytes.len());
+            buf[..length].copy_from_slice(&bytes[..length]);
+            bytes.drain(..length);
+
+            if bytes.is_empty() {
+                self.output.pop_front();
+            }
+        }
+
+        Ok(length)
+    }
+}
@@ -0,0 +1,292 @@
+//! # Bus sniffer
+//! Listens to a bus driven by another controller, such as an OpenCM or
+//! CM-530, decoding every instruction & status packet sent on it. The
+//! sniffer only ever reads from the connection, so it cannot disturb the
+//! bus.
+//!
+//! As only one device may talk on the bus at a time, each status packet is
+//! paired with the last instruction sent to the same servo, while an
+//! instruction followed by another instruction went unanswered. Status
+//! packets which respond to an instruction sent to several servos, such as
+//! a bulk read, are reported on their own.
+//!
+//! Pairing means packets are not always reported in the order they were
+//! seen, so when capturing the bus, each packet is instead recorded as it is
+//! decoded (see [`Sniffer::start_capture`]).
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::dissector::Dissector;
+//! use movement::dynamixel::sniffer::{Sniffed, Sniffer};
+//!
+//! fn main() {
+//!     // A read of the Present Position & its response, then an unanswered ping
+//!     let bus: &[u8] = &[
+//!         0xFF, 0xFF, 0x01, 0x04, 0x02, 0x24, 0x02, 0xD2,
+//!         0xFF, 0xFF, 0x01, 0x04, 0x00, 0x00, 0x02, 0xF8,
+//!         0xFF, 0xFF, 0x02, 0x02, 0x01, 0xFA,
+//!     ];
+//!     let mut sniffer = Sniffer::new(bus, Dissector::new(builtin_model(12)));
+//!
+//!     let sniffed = sniffer.poll().unwrap();
+//!     match &sniffed[0] {
+//!         Sniffed::Exchange { request, response } => {
+//!             assert_eq!(request.frame.id, 1);
+//!             let response = response.as_ref().unwrap();
+//!             assert_eq!(response.frame.fields[0].to_string(), "Present Position (36) = 512");
+//!         }
+//!         other => panic!("expected an exchange, found {:?}", other),
+//!     }
+//!
+//!     // The ping is only known to be unanswered once sniffing ends
+//!     let sniffed = sniffer.finish();
+//!     assert!(matches!(&sniffed[0], Sniffed::Exchange { response: None, .. }));
+//! }
+//! ```
+
+use super::capture::{CapturedPacket, Direction};
+use super::dissector::{Dissector, Frame, FrameKind, Segment};
+use super::DynamixelError;
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+/// The broadcast ID, to which servos never respond individually
+const BROADCAST_ID: u8 = 0xFE;
+
+/// A packet seen on the bus, and the time since sniffing started at which
+/// its last byte was read. Packets read from the connection at once share
+/// the same time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SniffedFrame {
+    pub time: Duration,
+    pub frame: Frame,
+}
+
+impl std::fmt::Display for SniffedFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:>10.3}ms {}",
+            self.time.as_micros() as f64 / 1000.0,
+            self.frame
+        )
+    }
+}
+
+/// Everything seen on the bus, grouped into exchanges between the
+/// controller & the servos
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Sniffed {
+    /// An instruction, along with the status packet sent in response if the
+    /// servo responded
+    Exchange {
+        request: SniffedFrame,
+        response: Option<SniffedFrame>,
+    },
+    /// A status packet which does not respond to the last instruction
+    Status(SniffedFrame),
+    /// Bytes which do not form a packet
+    Unframed(Vec<u8>),
+}
+
+impl std::fmt::Display for Sniffed {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Sniffed::Exchange { request, response } => {
+                write!(f, "{}", request)?;
+                match response {
+                    Some(response) => write!(
+                        f,
+                        "\n{}\n    Responded after {}µs",
+                        response,
+                        (response.time - request.time).as_micros()
+                    ),
+                    None if request.frame.id == BROADCAST_ID => Ok(()),
+                    None => write!(f, "\n    !! No response"),
+                }
+            }
+            Sniffed::Status(status) => write!(f, "{}", status),
+            Sniffed::Unframed(bytes) => write!(f, "{}", Segment::Unframed(bytes.clone())),
+        }
+    }
+}
+
+/// Passively decodes the packets read from a connection
+pub struct Sniffer<R: Read> {
+    connection: R,
+    dissector: Dissector,
+    buffer: Vec<u8>,
+    /// The time each read into the buffer was made, along with the length
+    /// of the buffer after it
+    reads: Vec<(usize, Duration)>,
+    outstanding: Option<SniffedFrame>,
+    captured: Option<Vec<CapturedPacket>>,
+    start: Instant,
+}
+
+impl<R: Read> Sniffer<R> {
+    /// Starts sniffing the connection, naming items using the dissector
+    pub fn new(connection: R, dissector: Dissector) -> Sniffer<R> {
+        Sniffer {
+            connection,
+            dissector,
+            buffer: vec![],
+            reads: vec![],
+            outstanding: None,
+            captured: None,
+            start: Instant::now(),
+        }
+    }
+
+    /// Reads whatever has been sent on the bus since the last poll,
+    /// returning every packet seen. An instruction is only returned once
+    /// its response has been seen, or once it is known there is none. Any
+    /// incomplete packet is decoded when the bus falls silent.
+    pub fn poll(&mut self) -> Result<Vec<Sniffed>, DynamixelError> {
+        let mut buf = [0u8; 256];
+        let length = match self.connection.read(&mut buf) {
+            Ok(length) => length,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                0
+            }
+            Err(err) => return Err(DynamixelError::Io(err)),
+        };
+
+        let (segments, used) = if length == 0 {
+            let segments = self.dissector.dissect(&self.buffer);
+            (segments, self.buffer.len())
+        } else {
+            self.buffer.extend(&buf[..length]);
+            self.reads.push((self.buffer.len(), self.start.elapsed()));
+            self.dissector.dissect_partial(&self.buffer)
+        };
+
+        Ok(self.sniff(segments, used))
+    }
+
+    /// Starts keeping every packet & any unframed bytes as they are decoded,
+    /// in the order they were seen on the bus, along with the time each was
+    /// read. Instructions are recorded as sent, and everything else as
+    /// received.
+    pub fn start_capture(&mut self) {
+        self.captured = Some(vec![]);
+    }
+
+    /// Takes the packets decoded since the last call, if the capture has
+    /// been started
+    pub fn take_captured(&mut self) -> Vec<CapturedPacket> {
+        self.captured.as_mut().map_or_else(Vec::new, std::mem::take)
+    }
+
+    /// Stops sniffing, returning any incomplete packet & any instruction
+    /// still awaiting a response. Anything decoded is captured, so can be
+    /// taken afterwards.
+    pub fn finish(&mut self) -> Vec<Sniffed> {
+        let segments = self.dissector.dissect(&self.buffer);
+        let used = self.buffer.len();
+        let mut sniffed = self.sniff(segments, used);
+
+        if let Some(request) = self.outstanding.take() {
+            sniffed.push(Sniffed::Exchange {
+                request,
+                response: None,
+            });
+        }
+
+        sniffed
+    }
+
+    /// Pairs each packet decoded from the first `used` bytes of the buffer
+    /// with its instruction, then removes those bytes from the buffer
+    fn sniff(&mut self, segments: Vec<Segment>, used: usize) -> Vec<Sniffed> {
+        let mut sniffed = vec![];
+        let mut end = 0;
+        for segment in segments {
+            match segment {
+                Segment::Frame(frame) => {
+                    end += frame.bytes.len();
+                    let time = self.read_time(end);
+                    let direction = match frame.kind {
+                        FrameKind::Instruction(_) => Direction::Sent,
+                        FrameKind::Status(_) => Direction::Received,
+                    };
+                    self.capture(time, direction, &frame.bytes);
+                    self.pair(SniffedFrame { time, frame }, &mut sniffed)
+                }
+                Segment::Unframed(bytes) => {
+                    end += bytes.len();
+                    self.capture(self.read_time(end), Direction::Received, &bytes);
+                    sniffed.push(Sniffed::Unframed(bytes))
+                }
+            }
+        }
+
+        self.buffer.drain(..used);
+        self.reads.retain(|(length, _)| *length > used);
+        for (length, _) in self.reads.iter_mut() {
+            *length -= used;
+        }
+
+        sniffed
+    }
+
+    /// Keeps the bytes of a packet, if the capture has been started
+    fn capture(&mut self, time: Duration, direction: Direction, bytes: &[u8]) {
+        if let Some(captured) = self.captured.as_mut() {
+            captured.push(CapturedPacket {
+                time: time.as_micros() as u64,
+                direction,
+                bytes: bytes.to_vec(),
+            });
+        }
+    }
+
+    /// The time at which the byte before `end` in the buffer was read
+    fn read_time(&self, end: usize) -> Duration {
+        self.reads
+            .iter()
+            .find(|(length, _)| *length >= end)
+            .map_or_else(|| self.start.elapsed(), |(_, time)| *time)
+    }
+
+    /// Pairs a packet with the outstanding instruction
+    fn pair(&mut self, sniffed_frame: SniffedFrame, sniffed: &mut Vec<Sniffed>) {
+        match sniffed_frame.frame.kind {
+            FrameKind::Instruction(_) => {
+                // The last instruction was not responded to
+                if let Some(request) = self.outstanding.take() {
+                    sniffed.push(Sniffed::Exchange {
+                        request,
+                        response: None,
+                    });
+                }
+
+                if sniffed_frame.frame.id == BROADCAST_ID {
+                    sniffed.push(Sniffed::Exchange {
+                        request: sniffed_frame,
+                        response: None,
+                    });
+                } else {
+                    self.outstanding = Some(sniffed_frame);
+                }
+            }
+            FrameKind::Status(_) => match self.outstanding.take() {
+                Some(request) if request.frame.id == sniffed_frame.frame.id => {
+                    sniffed.push(Sniffed::Exchange {
+                        request,
+                        response: Some(sniffed_frame),
+                    });
+                }
+                outstanding => {
+                    self.outstanding = outstanding;
+                    sniffed.push(Sniffed::Status(sniffed_frame));
+                }
+            },
+        }
+    }
+}
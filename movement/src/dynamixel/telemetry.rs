@@ -0,0 +1,216 @@
+//! # Telemetry
+//! Every item in the Sensor category of a servo's control table, such as its
+//! Present Temperature, Present Voltage & Present Load, is exposed as a
+//! [`DataSensor`] in the `sensors` of the `Dynamixel` once its model is
+//! known. This allows every sensor of every servo to be enumerated without
+//! knowing anything about Dynamixels.
+//!
+//! Sensors report the last value read from the servo rather than reading it
+//! themselves, as a single read of every sensor is far cheaper than reading
+//! each in turn. The values are refreshed either by `read_sensors`, or by
+//! storing the data from a bulk read in the `sensor_cache` of the servo.
+//! Sensors report a value of 0 until the servo has been read.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::protocol_one::ProtocolOne;
+//! use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+//! use movement::dynamixel::{Dynamixel, DynamixelID, Protocol};
+//! use sensor::DataUnit;
+//!
+//! fn main() {
+//!     let mut bus = SimulatedBus::new();
+//!     bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+//!     bus.servo_mut(1).unwrap().set_item("Present Voltage", 118);
+//!     let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(1));
+//!     dxl.detect_model().unwrap();
+//!     dxl.read_sensors(Protocol::One).unwrap();
+//!
+//!     let voltage = dxl.sensors["Present Voltage"].get_data();
+//!     assert_eq!((voltage.unit, voltage.value), (DataUnit::Volts, 118));
+//!
+//!     let temperature = dxl.sensors["Present Temperature"].get_data();
+//!     assert_eq!(temperature.unit, DataUnit::DegreesCelcius);
+//! }
+//! ```
+
+use super::control_table::ControlTableItem;
+use super::protocol_one::ProtocolOne;
+use super::protocol_two::ProtocolTwo;
+use super::units::Resolution;
+use super::{ControlTableType, Dynamixel, DynamixelError, Protocol};
+use sensor::{DataSensor, DataValue};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// The last bytes read from each address of a servo's control table, shared
+/// between the servo and its sensors
+#[derive(Clone, Debug, Default)]
+pub struct SensorCache {
+    bytes: Arc<Mutex<HashMap<u16, u8>>>,
+}
+
+impl SensorCache {
+    /// Creates a cache without any data
+    pub fn new() -> SensorCache {
+        SensorCache::default()
+    }
+
+    /// Stores data read from the servo, starting at the given address. Any
+    /// data past the last address of the control table is ignored.
+    ///
+    /// ```
+    /// use movement::dynamixel::telemetry::SensorCache;
+    ///
+    /// fn main() {
+    ///     // Data from a bulk read of the Present Position & Present Speed
+    ///     let cache = SensorCache::new();
+    ///     cache.update(36, &[0x00, 0x02, 0x10, 0x04]);
+    ///
+    ///     assert_eq!(cache.get(36, 2), Some(512));
+    ///     assert_eq!(cache.get(38, 2), Some(1040));
+    ///     assert_eq!(cache.get(40, 2), None);
+    /// }
+    /// ```
+    pub fn update(&self, address: u16, data: &[u8]) {
+        let mut bytes = self.lock();
+        for (offset, byte) in data.iter().enumerate() {
+            let address = match u16::try_from(offset)
+                .ok()
+                .and_then(|offset| address.checked_add(offset))
+            {
+                Some(address) => address,
+                None => break,
+            };
+            bytes.insert(address, *byte);
+        }
+    }
+
+    /// Gets the little endian value of an item, if every byte of it has been
+    /// read
+    pub fn get(&self, address: u16, size: u8) -> Option<u64> {
+        let bytes = self.lock();
+        (0..size as u16).rev().try_fold(0, |value, offset| {
+            address
+                .checked_add(offset)
+                .and_then(|address| bytes.get(&address))
+                .map(|byte| value << 8 | *byte as u64)
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u16, u8>> {
+        self.bytes.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// A sensor item of a servo's control table
+pub struct ServoSensor {
+    item: ControlTableItem,
+    resolution: Option<Resolution>,
+    cache: SensorCache,
+}
+
+impl ServoSensor {
+    /// Creates a sensor reporting an item from the cache. Without the
+    /// resolution of the model, raw values are reported in the item's units.
+    pub fn new(
+        item: ControlTableItem,
+        resolution: Option<Resolution>,
+        cache: SensorCache,
+    ) -> ServoSensor {
+        ServoSensor {
+            item,
+            resolution,
+            cache,
+        }
+    }
+
+    /// The name of the control table item
+    pub fn name(&self) -> &str {
+        &self.item.name
+    }
+}
+
+impl DataSensor<isize> for ServoSensor {
+    fn get_data(&self) -> DataValue<isize> {
+        let raw = self
+            .cache
+            .get(self.item.data.address, self.item.data.size)
+            .unwrap_or(0);
+
+        match self.resolution {
+            Some(resolution) => resolution.to_physical(&self.item, raw),
+            None => None,
+        }
+        .unwrap_or(DataValue {
+            unit: self.item.data.units.unwrap_or(sensor::DataUnit::Other),
+            power: 0,
+            value: raw as isize,
+        })
+    }
+}
+
+impl<C> Dynamixel<C>
+where
+    C: Read + Write,
+{
+    /// Reads every sensor item with a single read instruction, updating the
+    /// values reported by the servo's sensors. Nothing is read if the model
+    /// of the servo is not known.
+    pub fn read_sensors(&mut self, protocol: Protocol) -> Result<(), DynamixelError> {
+        let span = self
+            .sensor_items()
+            .map(|item| (item.data.address, item.data.address + item.data.size as u16))
+            .fold(None, |span: Option<(u16, u16)>, (start, end)| match span {
+                Some((min, max)) => Some((min.min(start), max.max(end))),
+                None => Some((start, end)),
+            });
+        let (start, end) = match span {
+            Some(span) => span,
+            None => return Ok(()),
+        };
+
+        let data = match protocol {
+            Protocol::One => {
+                let address = u8::try_from(start).map_err(|_| {
+                    DynamixelError::InvalidPacket(format!(
+                        "Address {} is out of range for protocol 1!",
+                        start
+                    ))
+                })?;
+                ProtocolOne::read(self, address, (end - start) as u64)?.parameters
+            }
+            Protocol::Two => ProtocolTwo::read(self, start, end - start)?.parameters,
+        };
+        self.sensor_cache.update(start, &data);
+
+        Ok(())
+    }
+
+    /// Creates a sensor for every sensor item of the servo's model
+    pub(crate) fn load_sensors(&mut self) {
+        let resolution = self
+            .model
+            .as_ref()
+            .and_then(|model| Resolution::of(model.number));
+        let sensors: Vec<ServoSensor> = self
+            .sensor_items()
+            .map(|item| ServoSensor::new(item.clone(), resolution, self.sensor_cache.clone()))
+            .collect();
+
+        for sensor in sensors {
+            self.sensors
+                .insert(sensor.name().to_string(), Box::new(sensor));
+        }
+    }
+
+    /// Every item in the Sensor category of the servo's control table
+    fn sensor_items(&self) -> impl Iterator<Item = &ControlTableItem> {
+        self.model
+            .iter()
+            .flat_map(|model| model.items.iter())
+            .filter(|item| item.category == ControlTableType::Sensor)
+    }
+}
@@ -0,0 +1,244 @@
+//! # Physical units
+//! The control table stores positions, speeds & loads as raw values whose
+//! meaning depends on the model: a position of 512 is 150° on an AX-12A, but
+//! 45° on an MX-28. This file converts between raw values and physical
+//! quantities, which are given as a [`DataValue`] scaled by a power of 10:
+//!
+//! | Item units           | Physical units       | Power |
+//! |----------------------|----------------------|-------|
+//! | Pulse                | Degrees              | -2    |
+//! | RevolutionsPerMinute | RevolutionsPerMinute | -3    |
+//! | Percentage           | Percentage           | -1    |
+//! | Volts                | Volts                | -1    |
+//! | DegreesCelcius       | DegreesCelcius       | 0     |
+//! | Amps                 | Amps                 | -4    |
+//! | Second               | Second               | -6    |
+//!
+//! Positions may also be given in radians when they are written. On protocol
+//! 1, speeds & loads give their direction with bit 10, which is returned as a
+//! negative value when set (clockwise), while protocol 2 stores them signed.
+//!
+//! ```
+//! use movement::dynamixel::control_table::builtin_model;
+//! use movement::dynamixel::units::Resolution;
+//! use sensor::{DataUnit, DataValue};
+//!
+//! fn main() {
+//!     let model = builtin_model(12).unwrap();
+//!     let resolution = Resolution::of(model.number).unwrap();
+//!
+//!     let position = model.item("Present Position").unwrap();
+//!     let angle = resolution.to_physical(position, 512).unwrap();
+//!     assert_eq!(angle, DataValue { unit: DataUnit::Degrees, power: -2, value: 15000 });
+//!
+//!     // Turning clockwise at 111 RPM
+//!     let speed = model.item("Present Speed").unwrap();
+//!     let rpm = resolution.to_physical(speed, 1024 + 1000).unwrap();
+//!     assert_eq!(rpm.value, -111_000);
+//!
+//!     let goal = model.item("Goal Position").unwrap();
+//!     let right_angle = DataValue::from_f64(DataUnit::Radians, -4, std::f64::consts::FRAC_PI_2);
+//!     assert_eq!(resolution.from_physical(goal, &right_angle).unwrap(), 307);
+//! }
+//! ```
+
+use super::control_table::ControlTableItem;
+use super::DynamixelError;
+use sensor::{DataUnit, DataValue};
+use std::convert::TryFrom;
+
+/// The bit giving the direction of speeds & loads on protocol 1 servos
+const DIRECTION_BIT: i64 = 1 << 10;
+
+/// The physical size of a single raw unit of each kind of item on a model
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resolution {
+    /// Degrees per position unit
+    pub position: f64,
+    /// RPM per speed unit
+    pub speed: f64,
+    /// Percent per load or torque unit
+    pub load: f64,
+    /// Percent per PWM unit
+    pub pwm: f64,
+    /// Volts per voltage unit
+    pub voltage: f64,
+    /// Amps per current unit, and the raw value at which no current flows
+    pub current: (f64, i64),
+    /// Speeds & loads give their direction with bit 10, rather than a sign
+    pub direction_bit: bool,
+}
+
+/// The AX series, which turns through 300° in joint mode
+const AX_SERIES: Resolution = Resolution {
+    position: 300.0 / 1024.0,
+    speed: 0.111,
+    load: 0.1,
+    pwm: 0.1,
+    voltage: 0.1,
+    current: (0.0045, 2048),
+    direction_bit: true,
+};
+
+/// The MX series on protocol 1
+const MX_SERIES: Resolution = Resolution {
+    position: 360.0 / 4096.0,
+    speed: 0.114,
+    load: 0.1,
+    pwm: 0.1,
+    voltage: 0.1,
+    current: (0.0045, 2048),
+    direction_bit: true,
+};
+
+/// The X series on protocol 2
+const X_SERIES: Resolution = Resolution {
+    position: 360.0 / 4096.0,
+    speed: 0.229,
+    load: 0.1,
+    pwm: 100.0 / 885.0,
+    voltage: 0.1,
+    current: (0.00269, 0),
+    direction_bit: false,
+};
+
+impl Resolution {
+    /// Gets the resolution of a built-in model by its model number
+    pub fn of(model_number: u16) -> Option<Resolution> {
+        match model_number {
+            12 | 18 => Some(AX_SERIES),
+            29 | 310 | 320 => Some(MX_SERIES),
+            1020 | 1030 | 1060 => Some(X_SERIES),
+            _ => None,
+        }
+    }
+
+    /// Converts the raw value of an item into a physical quantity, returning
+    /// `None` if the item has no physical units
+    pub fn to_physical(&self, item: &ControlTableItem, raw: u64) -> Option<DataValue<isize>> {
+        let (scale, unit, power) = self.scale(item)?;
+        let value = self.decode(item, raw) as f64 * scale;
+
+        Some(DataValue::from_f64(unit, power, value))
+    }
+
+    /// Converts a physical quantity into the raw value of an item. The
+    /// quantity must be given in the physical units of the item, except for
+    /// angles, which may be given in either degrees or radians.
+    pub fn from_physical(
+        &self,
+        item: &ControlTableItem,
+        value: &DataValue<isize>,
+    ) -> Result<u64, DynamixelError> {
+        let (scale, unit, _) = self
+            .scale(item)
+            .ok_or_else(|| DynamixelError::UnitMismatch {
+                name: item.name.clone(),
+                expected: None,
+                found: value.unit,
+            })?;
+
+        let physical = match (unit, value.unit) {
+            (DataUnit::Degrees, DataUnit::Radians) => value.as_f64().to_degrees(),
+            (expected, found) if expected == found => value.as_f64(),
+            (expected, found) => {
+                return Err(DynamixelError::UnitMismatch {
+                    name: item.name.clone(),
+                    expected: Some(expected),
+                    found,
+                })
+            }
+        };
+
+        self.encode(item, (physical / scale).round() as i64)
+    }
+
+    /// The size of a raw unit of the item, along with the physical units &
+    /// power of 10 it is converted to
+    fn scale(&self, item: &ControlTableItem) -> Option<(f64, DataUnit, isize)> {
+        match item.data.units? {
+            DataUnit::Pulse | DataUnit::Degrees => Some((self.position, DataUnit::Degrees, -2)),
+            DataUnit::RevolutionsPerMinute => {
+                Some((self.speed, DataUnit::RevolutionsPerMinute, -3))
+            }
+            DataUnit::Percentage if item.name.contains("PWM") => {
+                Some((self.pwm, DataUnit::Percentage, -1))
+            }
+            DataUnit::Percentage => Some((self.load, DataUnit::Percentage, -1)),
+            DataUnit::Volts => Some((self.voltage, DataUnit::Volts, -1)),
+            DataUnit::DegreesCelcius => Some((1.0, DataUnit::DegreesCelcius, 0)),
+            DataUnit::Amps => Some((self.current.0, DataUnit::Amps, -4)),
+            // The return delay time is counted in units of 2µs
+            DataUnit::Second => Some((0.000_002, DataUnit::Second, -6)),
+            DataUnit::Radians | DataUnit::Other => None,
+        }
+    }
+
+    /// How negative values of the item are stored
+    fn encoding(&self, item: &ControlTableItem) -> Encoding {
+        let signed_range = matches!(item.data.range, Some((min, _)) if min < 0);
+        match item.data.units {
+            Some(DataUnit::RevolutionsPerMinute) | Some(DataUnit::Percentage) => {
+                if self.direction_bit {
+                    Encoding::Direction
+                } else {
+                    Encoding::TwosComplement
+                }
+            }
+            Some(DataUnit::Amps) if self.direction_bit => Encoding::Offset(self.current.1),
+            Some(DataUnit::Amps) => Encoding::TwosComplement,
+            Some(DataUnit::Pulse) if !self.direction_bit || signed_range => {
+                Encoding::TwosComplement
+            }
+            _ => Encoding::Unsigned,
+        }
+    }
+
+    /// Converts a raw value into a signed number of units
+    fn decode(&self, item: &ControlTableItem, raw: u64) -> i64 {
+        let raw = raw as i64;
+        match self.encoding(item) {
+            Encoding::Unsigned => raw,
+            Encoding::Offset(zero) => raw - zero,
+            Encoding::Direction if raw & DIRECTION_BIT != 0 => -(raw & (DIRECTION_BIT - 1)),
+            Encoding::Direction => raw & (DIRECTION_BIT - 1),
+            Encoding::TwosComplement => {
+                // Sign extend the value from the size of the item
+                let unused = 64 - 8 * item.data.size as u32;
+                (raw << unused) >> unused
+            }
+        }
+    }
+
+    /// Converts a signed number of units into a raw value
+    fn encode(&self, item: &ControlTableItem, units: i64) -> Result<u64, DynamixelError> {
+        let out_of_range = || DynamixelError::OutOfRange {
+            name: item.name.clone(),
+            value: units,
+        };
+
+        match self.encoding(item) {
+            Encoding::Unsigned => u64::try_from(units).map_err(|_| out_of_range()),
+            Encoding::Offset(zero) => u64::try_from(units + zero).map_err(|_| out_of_range()),
+            Encoding::Direction if units.abs() >= DIRECTION_BIT => Err(out_of_range()),
+            Encoding::Direction if units < 0 => Ok((-units | DIRECTION_BIT) as u64),
+            Encoding::Direction => Ok(units as u64),
+            Encoding::TwosComplement => {
+                // Store the two's complement in the size of the item
+                let mask = u64::MAX >> (64 - 8 * item.data.size as u32);
+                Ok(units as u64 & mask)
+            }
+        }
+    }
+}
+
+/// The ways in which items store their sign
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Unsigned,
+    /// Bit 10 is set for clockwise values
+    Direction,
+    /// The given raw value represents 0
+    Offset(i64),
+    TwosComplement,
+}
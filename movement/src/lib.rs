@@ -2,12 +2,14 @@ pub mod dynamixel;
 
 /// Generic functionality that should be exposed by any connected servo
 pub trait Servo {
-    fn set_pos(&mut self, pos: usize) -> Result<(), String>;
+    type Error;
+    fn set_pos(&mut self, pos: usize) -> Result<(), Self::Error>;
 }
 
 /// Generic functionality that should be exposed by any connected motor
 pub trait Motor {
-    fn set_speed(&mut self, speed: usize) -> Result<(), String>;
+    type Error;
+    fn set_speed(&mut self, speed: usize) -> Result<(), Self::Error>;
     fn get_speed(&self) -> usize;
     fn get_max_speed(&self) -> usize;
 }
@@ -0,0 +1,196 @@
+use movement::dynamixel::bus::DynamixelBus;
+use movement::dynamixel::capture::{Capture, Direction, ReplayConnection};
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::motion::MotionTransaction;
+use movement::dynamixel::protocol_one::ProtocolOne;
+use movement::dynamixel::reliability::RetryPolicy;
+use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+use movement::dynamixel::{BulkReadPacket, DynamixelError, SyncPacket};
+use std::io::{Read, Write};
+use std::time::Duration;
+
+fn port(ids: &[u8]) -> SimulatedBus {
+    let mut port = SimulatedBus::new();
+    for &id in ids {
+        port.add_servo(SimulatedDynamixel::new(id, builtin_model(29).unwrap()));
+    }
+
+    port
+}
+
+fn model_numbers(ids: &[u8]) -> Vec<BulkReadPacket> {
+    ids.iter()
+        .map(|&id| BulkReadPacket {
+            id,
+            length: 2,
+            address: 0,
+        })
+        .collect()
+}
+
+fn patient(retries: u32) -> RetryPolicy {
+    RetryPolicy {
+        retries,
+        timeout: Some(Duration::from_millis(40)),
+        ..RetryPolicy::default()
+    }
+}
+
+#[test]
+fn bulk_read_waits_past_the_port_timeout() {
+    let mut port = port(&[1, 2]);
+    port.delay_replies(Duration::from_millis(10));
+    let bus = DynamixelBus::with_timing(port, 1_000_000, patient(0));
+
+    let results = bus.bulk_read(model_numbers(&[1, 2])).unwrap();
+    assert_eq!(results[&1].as_ref().unwrap(), &vec![29, 0]);
+    assert_eq!(results[&2].as_ref().unwrap(), &vec![29, 0]);
+}
+
+#[test]
+fn bulk_read_retries_missing_servos() {
+    let mut port = port(&[1, 2]);
+    port.drop_replies(1);
+    let bus = DynamixelBus::with_timing(port, 1_000_000, patient(1));
+
+    let results = bus.bulk_read(model_numbers(&[1, 2])).unwrap();
+    assert!(results.values().all(Result::is_ok));
+
+    // Only the servo whose response was lost is read again
+    let statistics = bus.statistics();
+    assert_eq!((statistics.get(1).sent, statistics.get(1).retries), (2, 1));
+    assert_eq!((statistics.get(2).sent, statistics.get(2).retries), (1, 0));
+}
+
+#[test]
+fn bulk_read_gives_up_after_the_retries() {
+    let bus = DynamixelBus::with_timing(port(&[1]), 1_000_000, patient(2));
+
+    let results = bus.bulk_read(model_numbers(&[1, 3])).unwrap();
+    assert!(results[&1].is_ok());
+    assert!(matches!(results[&3], Err(DynamixelError::Timeout)));
+    assert_eq!(bus.statistics().get(3).sent, 3);
+}
+
+#[test]
+fn handles_use_the_timing_of_the_bus() {
+    let mut port = port(&[1]);
+    port.delay_replies(Duration::from_millis(10));
+    let bus = DynamixelBus::with_timing(port, 1_000_000, patient(0));
+
+    let mut servo = bus.servo(1);
+    assert_eq!(servo.transaction(|dxl| dxl.detect_model()).unwrap(), 29);
+}
+
+#[test]
+fn motion_waits_past_the_port_timeout() {
+    let mut port = port(&[1, 2]);
+    port.delay_replies(Duration::from_millis(10));
+    let bus = DynamixelBus::with_timing(port, 1_000_000, patient(0));
+
+    MotionTransaction::new()
+        .goal_position(1, 200)
+        .goal_position(2, 800)
+        .execute(&bus)
+        .unwrap();
+}
+
+#[test]
+fn motion_retries_lost_acknowledgements() {
+    let mut port = port(&[1]);
+    port.drop_replies(1);
+    let bus = DynamixelBus::with_timing(port, 1_000_000, patient(1));
+
+    MotionTransaction::new()
+        .goal_position(1, 200)
+        .execute(&bus)
+        .unwrap();
+    bus.transaction(|port| {
+        assert_eq!(port.servo(1).unwrap().get_item("Goal Position"), Some(200));
+    });
+}
+
+#[test]
+fn motion_is_counted_in_the_statistics() {
+    let mut port = port(&[1]);
+    port.drop_replies(1);
+    let bus = DynamixelBus::with_timing(port, 1_000_000, patient(1));
+
+    MotionTransaction::new()
+        .goal_position(1, 200)
+        .execute(&bus)
+        .unwrap();
+
+    // The read of the original goal is retried, then the move is registered
+    let statistics = bus.statistics().get(1);
+    assert_eq!((statistics.sent, statistics.received), (3, 2));
+    assert_eq!((statistics.timeouts, statistics.retries), (1, 1));
+}
+
+#[test]
+fn failed_motion_is_counted_in_the_statistics() {
+    let bus = DynamixelBus::with_timing(port(&[1]), 1_000_000, patient(1));
+
+    let result = MotionTransaction::new()
+        .goal_position(1, 200)
+        .goal_position(3, 200)
+        .execute(&bus);
+    assert!(matches!(result, Err(DynamixelError::Motion(_))));
+
+    // Servo 1 is rolled back with a registered write & an action
+    assert_eq!(bus.statistics().get(1).sent, 4);
+    assert_eq!(bus.statistics().get(3).timeouts, 2);
+}
+
+/// Turns on the LED of servos 1 & 2, reads their model numbers, then moves
+/// them together
+fn drive<C: Read + Write>(bus: &DynamixelBus<C>) -> Vec<Vec<u8>> {
+    let packets = vec![
+        SyncPacket {
+            id: 1,
+            data: 1,
+            address: 25,
+        },
+        SyncPacket {
+            id: 2,
+            data: 1,
+            address: 25,
+        },
+    ];
+    bus.sync_write(packets, 1).unwrap();
+
+    let results = bus.bulk_read(model_numbers(&[1, 2])).unwrap();
+    MotionTransaction::new()
+        .goal_position(1, 200)
+        .goal_position(2, 800)
+        .execute(bus)
+        .unwrap();
+
+    vec![
+        results[&1].as_ref().unwrap().clone(),
+        results[&2].as_ref().unwrap().clone(),
+    ]
+}
+
+#[test]
+fn bus_traffic_is_captured() {
+    let bus = DynamixelBus::new(port(&[1, 2]));
+    bus.start_capture();
+    let results = drive(&bus);
+    let capture = bus.stop_capture().unwrap();
+
+    // The sync write, the bulk read & the broadcast action of the motion
+    let broadcasts = capture
+        .packets
+        .iter()
+        .filter(|packet| packet.direction == Direction::Sent && packet.bytes[2] == 0xFE)
+        .count();
+    assert_eq!(broadcasts, 3);
+
+    // The same run is reproduced without any servos
+    let saved = capture.to_toml().unwrap();
+    let replay = ReplayConnection::new(Capture::from_toml(&saved).unwrap());
+    let bus = DynamixelBus::new(replay);
+    assert_eq!(drive(&bus), results);
+    assert_eq!(bus.transaction(|replay| replay.remaining()), 0);
+}
@@ -0,0 +1,52 @@
+use movement::dynamixel::control_table::{ControlTableError, Model};
+
+fn model(items: &str) -> Result<Model, ControlTableError> {
+    Model::from_toml(&format!("number = 1\nname = \"Test\"\n{}", items))
+}
+
+fn item(name: &str, address: u16, size: u8) -> String {
+    format!(
+        "[[items]]\nname = \"{}\"\ncategory = \"Component\"\naddress = {}\nsize = {}\naccess = \"ReadWrite\"\n",
+        name, address, size
+    )
+}
+
+#[test]
+fn item_past_the_last_address_is_rejected() {
+    let items = item("Last", u16::MAX, 2);
+
+    assert!(matches!(
+        model(&items),
+        Err(ControlTableError::InvalidAddress {
+            address: u16::MAX,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn item_at_the_last_address_is_accepted() {
+    let items = item("First", 0, 4) + &item("Last", u16::MAX, 1);
+
+    assert!(model(&items).is_ok());
+}
+
+#[test]
+fn overlap_near_the_last_address_is_rejected() {
+    let items = item("First", u16::MAX - 3, 4) + &item("Last", u16::MAX, 1);
+
+    assert!(matches!(
+        model(&items),
+        Err(ControlTableError::Overlap { .. })
+    ));
+}
+
+#[test]
+fn invalid_size_is_rejected() {
+    let items = item("Odd", 0, 3);
+
+    assert!(matches!(
+        model(&items),
+        Err(ControlTableError::InvalidSize { size: 3, .. })
+    ));
+}
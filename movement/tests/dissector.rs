@@ -0,0 +1,181 @@
+use movement::dynamixel::capture::{CapturedPacket, Direction};
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::dissector::{Dissector, FrameKind, Segment};
+use movement::dynamixel::protocol_one::{self, InstructionType, PacketType, StatusType};
+use movement::dynamixel::{protocol_two, DataBytes, Packet};
+
+/// A protocol 1 instruction packet
+fn sent(id: u8, instruction: InstructionType, params: Vec<u8>) -> Vec<u8> {
+    protocol_one::Packet::new_raw(id, PacketType::Instruction(instruction), params).to_bytes()
+}
+
+/// A protocol 1 status packet
+fn reply(id: u8, errors: Vec<StatusType>, params: Vec<u8>) -> Vec<u8> {
+    protocol_one::Packet::new_raw(id, PacketType::Status(errors), params).to_bytes()
+}
+
+fn kinds(segments: &[Segment]) -> Vec<FrameKind> {
+    segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Frame(frame) => frame.kind.clone(),
+            Segment::Unframed(bytes) => panic!("unframed bytes {:02X?}", bytes),
+        })
+        .collect()
+}
+
+fn instruction(name: &str) -> FrameKind {
+    FrameKind::Instruction(name.to_string())
+}
+
+fn status(errors: &str) -> FrameKind {
+    FrameKind::Status(errors.to_string())
+}
+
+#[test]
+fn read_after_unanswered_write_is_an_instruction() {
+    let mut dissector = Dissector::new(builtin_model(12));
+    let mut bytes = sent(1, InstructionType::Write, vec![25, 1]);
+    bytes.extend(sent(1, InstructionType::Read, vec![36, 2]));
+    bytes.extend(reply(1, vec![], vec![0x00, 0x02]));
+
+    let segments = dissector.dissect(&bytes);
+    assert_eq!(
+        kinds(&segments),
+        vec![instruction("Write"), instruction("Read"), status("OK")]
+    );
+    assert_eq!(
+        segments[2].to_string().lines().nth(1).unwrap().trim(),
+        "Present Position (36) = 512"
+    );
+}
+
+#[test]
+fn echo_is_an_instruction() {
+    let mut dissector = Dissector::new(None);
+    let ping = sent(1, InstructionType::Ping, vec![]);
+    let mut bytes = ping.clone();
+    bytes.extend(&ping);
+    bytes.extend(reply(1, vec![], vec![]));
+
+    assert_eq!(
+        kinds(&dissector.dissect(&bytes)),
+        vec![instruction("Ping"), instruction("Ping"), status("OK")]
+    );
+}
+
+#[test]
+fn bulk_read_responses_are_statuses() {
+    let mut dissector = Dissector::new(builtin_model(29));
+    let mut bytes = sent(
+        0xFE,
+        InstructionType::BulkRead,
+        vec![0x00, 2, 1, 36, 1, 2, 43],
+    );
+    // Servo 1 is overheating, which looks like a RegWrite
+    bytes.extend(reply(1, vec![StatusType::Overheating], vec![0x00, 0x08]));
+    bytes.extend(reply(2, vec![], vec![40]));
+
+    let segments = dissector.dissect(&bytes);
+    assert_eq!(
+        kinds(&segments),
+        vec![instruction("BulkRead"), status("Overheating"), status("OK")]
+    );
+    assert!(segments[2]
+        .to_string()
+        .contains("Present Temperature (43) = 40"));
+}
+
+#[test]
+fn captured_direction_is_used() {
+    let mut dissector = Dissector::new(None);
+    let sent = CapturedPacket {
+        time: 0,
+        direction: Direction::Sent,
+        bytes: sent(1, InstructionType::Write, vec![25, 1]),
+    };
+    // An unexpected packet which could be a RegWrite, but was received
+    let received = CapturedPacket {
+        time: 10,
+        direction: Direction::Received,
+        bytes: reply(1, vec![StatusType::Overheating], vec![0x00]),
+    };
+
+    assert_eq!(
+        kinds(&dissector.dissect_captured(&sent)),
+        vec![instruction("Write")]
+    );
+    assert_eq!(
+        kinds(&dissector.dissect_captured(&received)),
+        vec![status("Overheating")]
+    );
+}
+
+#[test]
+fn write_past_the_last_address_is_shown() {
+    let mut dissector = Dissector::new(None);
+    let write = protocol_two::Packet::write(1, u16::MAX, DataBytes::Four(0x0403_0201));
+
+    let frame = dissector.packet(&Packet::ProtocolTwo(write));
+    let fields: Vec<String> = frame.fields.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        fields,
+        vec!["Address 65535 = 01", "Past address 65535 = 02 03 04"]
+    );
+}
+
+fn protocol_two_bytes(packet: Packet) -> Vec<u8> {
+    match packet {
+        Packet::ProtocolTwo(packet) => packet.to_bytes(),
+        Packet::ProtocolOne(_) => panic!("expected a protocol 2 packet"),
+    }
+}
+
+/// A protocol 2 status packet without errors
+fn status_two(id: u8, params: Vec<u8>) -> Vec<u8> {
+    let status = protocol_two::PacketType::Status {
+        error: protocol_two::StatusType::Success,
+        alert: false,
+    };
+    protocol_two::Packet::new(id, status, params).to_bytes()
+}
+
+#[test]
+fn sync_read_responses_are_annotated() {
+    let mut dissector = Dissector::new(builtin_model(1020));
+    let mut bytes = protocol_two_bytes(protocol_two::sync_read(132, 4, &[1, 2]).unwrap());
+    bytes.extend(status_two(1, vec![0x00, 0x02, 0x00, 0x00]));
+    bytes.extend(status_two(2, vec![0x00, 0x04, 0x00, 0x00]));
+
+    let segments = dissector.dissect(&bytes);
+    assert_eq!(
+        kinds(&segments),
+        vec![instruction("SyncRead"), status("OK"), status("OK")]
+    );
+    assert!(segments[1]
+        .to_string()
+        .contains("Present Position (132) = 512"));
+    assert!(segments[2]
+        .to_string()
+        .contains("Present Position (132) = 1024"));
+}
+
+#[test]
+fn fast_sync_read_response_is_split_by_servo() {
+    let mut dissector = Dissector::new(builtin_model(1020));
+    let mut bytes = protocol_two_bytes(protocol_two::fast_sync_read(146, 1, &[1, 2]).unwrap());
+    // The data of servo 1 is followed by its CRC, then the error & data of
+    // servo 2, which is overloaded
+    let params = vec![1, 40, 0x12, 0x34, 0x80, 2, 45];
+    bytes.extend(status_two(0xFE, params));
+
+    let segments = dissector.dissect(&bytes);
+    assert_eq!(
+        kinds(&segments),
+        vec![instruction("FastSyncRead"), status("OK")]
+    );
+    let status = segments[1].to_string();
+    assert!(status.contains("ID 1: Present Temperature (146) = 40"));
+    assert!(status.contains("ID 2 = OK (alert)"));
+    assert!(status.contains("ID 2: Present Temperature (146) = 45"));
+}
@@ -0,0 +1,179 @@
+use movement::dynamixel::capture::{Capture, Direction, ReplayConnection};
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::protocol_one::{
+    Packet, PacketReadError, PacketType, ProtocolOne, StatusType,
+};
+use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+use movement::dynamixel::{
+    DataBytes, Dynamixel, DynamixelError, DynamixelID, DynamixelMode, RangePolicy,
+    StatusReturnLevel,
+};
+use std::time::Duration;
+
+fn servo(id: u8) -> Dynamixel<SimulatedBus> {
+    let mut bus = SimulatedBus::new();
+    bus.add_servo(SimulatedDynamixel::new(id, builtin_model(12).unwrap()));
+    let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(id));
+    dxl.set_model(builtin_model(12).unwrap());
+
+    dxl
+}
+
+fn replay_exchange(sent: &Packet, received: Vec<u8>) -> Dynamixel<ReplayConnection> {
+    let mut capture = Capture::new();
+    capture.record(Direction::Sent, sent.to_bytes());
+    capture.record(Direction::Received, received);
+
+    Dynamixel::new_empty(ReplayConnection::new(capture), DynamixelID::ID(1))
+}
+
+/// A status packet from servo 1
+fn status(errors: Vec<StatusType>, parameters: Vec<u8>) -> Vec<u8> {
+    Packet::new_raw(1, PacketType::Status(errors), parameters).to_bytes()
+}
+
+#[test]
+fn missing_servo_times_out() {
+    let mut bus = SimulatedBus::new();
+    bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+    let mut dxl = Dynamixel::new_empty(bus, DynamixelID::ID(2));
+
+    assert!(matches!(dxl.ping(), Err(DynamixelError::Timeout)));
+    assert_eq!(dxl.statistics.get(2).timeouts, 1);
+}
+
+#[test]
+fn short_reply_is_rejected() {
+    let request = Packet::read(1, 43, 2);
+    let mut dxl = replay_exchange(&request, status(vec![], vec![32]));
+
+    assert!(matches!(
+        dxl.read(43, 2),
+        Err(DynamixelError::Packet(PacketReadError::InvalidLength))
+    ));
+}
+
+#[test]
+fn corrupted_reply_after_echo_times_out() {
+    let request = Packet::ping(1);
+    let mut received = request.to_bytes();
+    let mut reply = status(vec![], vec![]);
+    *reply.last_mut().unwrap() ^= 0xFF;
+    received.extend(reply);
+    let mut dxl = replay_exchange(&request, received);
+
+    assert!(matches!(dxl.ping(), Err(DynamixelError::Timeout)));
+    let statistics = dxl.statistics.get(1);
+    assert_eq!((statistics.timeouts, statistics.checksum_errors), (1, 1));
+}
+
+#[test]
+fn reply_from_another_servo_is_rejected() {
+    let request = Packet::ping(1);
+    let reply = Packet::new_raw(2, PacketType::Status(vec![]), vec![]).to_bytes();
+    let mut dxl = replay_exchange(&request, reply);
+
+    assert!(matches!(
+        dxl.ping(),
+        Err(DynamixelError::IDMismatch {
+            expected: 1,
+            found: 2
+        })
+    ));
+}
+
+#[test]
+fn status_error_is_reported() {
+    let request = Packet::read(1, 43, 1);
+    let reply = status(vec![StatusType::Overheating], vec![80]);
+    let mut dxl = replay_exchange(&request, reply);
+
+    match dxl.read(43, 1) {
+        Err(DynamixelError::Status(errors)) => assert_eq!(errors, vec![StatusType::Overheating]),
+        result => panic!("expected a status error, found {:?}", result),
+    }
+}
+
+#[test]
+fn out_of_range_write_never_reaches_the_servo() {
+    let mut dxl = servo(1);
+
+    assert!(matches!(
+        ProtocolOne::write_item(&mut dxl, "Goal Position", 2000),
+        Err(DynamixelError::OutOfRange { .. })
+    ));
+    assert_eq!(dxl.statistics.get(1).sent, 0);
+
+    dxl.range_policy = RangePolicy::Clamp;
+    ProtocolOne::write_item(&mut dxl, "Goal Position", 2000).unwrap();
+    assert_eq!(
+        dxl.connection_handler
+            .servo(1)
+            .unwrap()
+            .get_item("Goal Position"),
+        Some(1023)
+    );
+}
+
+#[test]
+fn registered_write_waits_for_action() {
+    let mut dxl = servo(1);
+
+    dxl.register_write(25, DataBytes::One(1)).unwrap();
+    assert_eq!(dxl.read(25, 1).unwrap().parameters, vec![0]);
+
+    dxl.action().unwrap();
+    assert_eq!(dxl.read(25, 1).unwrap().parameters, vec![1]);
+}
+
+#[test]
+fn failed_status_return_level_write_is_not_applied() {
+    let mut dxl = servo(1);
+    let servo = dxl.connection_handler.servo_mut(1).unwrap();
+    servo.set_item("Status Return Level", 1).unwrap();
+    dxl.status_return_level = StatusReturnLevel::Read;
+    dxl.connection_handler.drop_replies(1);
+
+    assert!(matches!(
+        ProtocolOne::write_item(&mut dxl, "Status Return Level", 2),
+        Err(DynamixelError::Timeout)
+    ));
+    assert_eq!(dxl.status_return_level, StatusReturnLevel::Read);
+}
+
+#[test]
+fn status_return_level_applies_to_its_own_write() {
+    let mut dxl = servo(1);
+
+    // The servo stops responding as soon as the write takes effect
+    ProtocolOne::write_item(&mut dxl, "Status Return Level", 0).unwrap();
+    assert_eq!(dxl.status_return_level, StatusReturnLevel::PingOnly);
+    assert!(matches!(
+        ProtocolOne::read_item(&mut dxl, "Present Temperature"),
+        Err(DynamixelError::Timeout)
+    ));
+}
+
+#[test]
+fn failed_return_delay_write_is_not_applied() {
+    let mut dxl = servo(1);
+    dxl.connection_handler.drop_replies(1);
+
+    assert!(ProtocolOne::write_item(&mut dxl, "Return Delay Time", 100).is_err());
+    assert_eq!(dxl.return_delay, Duration::from_micros(500));
+
+    ProtocolOne::write_item(&mut dxl, "Return Delay Time", 100).unwrap();
+    assert_eq!(dxl.return_delay, Duration::from_micros(200));
+}
+
+#[test]
+fn angle_limit_write_forgets_the_mode() {
+    let mut dxl = servo(1);
+    dxl.set_mode(DynamixelMode::Wheel).unwrap();
+    dxl.connection_handler.drop_replies(1);
+
+    // The write reaches the servo even though its response is lost
+    assert!(ProtocolOne::write_item(&mut dxl, "CCW Angle Limit", 1023).is_err());
+    assert_eq!(dxl.mode, None);
+    assert_eq!(dxl.get_mode().unwrap(), DynamixelMode::Joint);
+}
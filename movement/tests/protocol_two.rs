@@ -0,0 +1,183 @@
+use movement::dynamixel::capture::{Capture, Direction, ReplayConnection};
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::protocol_one::PacketReadError;
+use movement::dynamixel::protocol_two::{crc, Packet, ProtocolTwo};
+use movement::dynamixel::{
+    DataBytes, Dynamixel, DynamixelError, DynamixelID, DynamixelMode, PacketManipulation, Protocol,
+    StatusReturnLevel,
+};
+use movement::{Motor, Servo};
+use std::time::Duration;
+
+const PING: [u8; 10] = [0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x03, 0x00, 0x01, 0x19, 0x4E];
+const PONG: [u8; 14] = [
+    0xFF, 0xFF, 0xFD, 0x00, 0x01, 0x07, 0x00, 0x55, 0x00, 0x06, 0x04, 0x26, 0x65, 0x5D,
+];
+
+fn replay(received: Vec<u8>) -> Dynamixel<ReplayConnection> {
+    replay_exchange(PING.to_vec(), received)
+}
+
+fn replay_exchange(sent: Vec<u8>, received: Vec<u8>) -> Dynamixel<ReplayConnection> {
+    let mut capture = Capture::new();
+    capture.record(Direction::Sent, sent);
+    capture.record(Direction::Received, received);
+
+    Dynamixel::new_empty(ReplayConnection::new(capture), DynamixelID::ID(1))
+}
+
+/// A successful status packet from servo 1
+fn status(parameters: &[u8]) -> Vec<u8> {
+    let length = parameters.len() as u16 + 4;
+    let mut bytes = vec![
+        0xFF,
+        0xFF,
+        0xFD,
+        0x00,
+        0x01,
+        length as u8,
+        (length >> 8) as u8,
+    ];
+    bytes.extend(&[0x55, 0x00]);
+    bytes.extend(parameters);
+    let chk = crc(&bytes);
+    bytes.extend(&chk.to_le_bytes());
+
+    bytes
+}
+
+#[test]
+fn echo_and_noise_are_skipped() {
+    let mut received = PING.to_vec();
+    received.extend(&[0x00, 0xFF, 0xFF, 0xFD]);
+    received.extend(&PONG);
+    let mut dxl = replay(received);
+
+    let status = ProtocolTwo::ping(&mut dxl).unwrap();
+    assert_eq!(status.parameters, vec![0x06, 0x04, 0x26]);
+}
+
+#[test]
+fn false_header_does_not_stall() {
+    // A header whose length field claims more data than will ever arrive
+    let mut received = vec![0xFF, 0xFF, 0xFD, 0x00, 0x01, 0xFF, 0x00];
+    received.extend(&PONG);
+    let mut dxl = replay(received);
+
+    assert_eq!(ProtocolTwo::ping(&mut dxl).unwrap().id, 1);
+}
+
+#[test]
+fn corrupted_reply_times_out() {
+    let mut received = PONG.to_vec();
+    received[12] ^= 0xFF;
+    let mut dxl = replay(received);
+
+    assert!(matches!(
+        ProtocolTwo::ping(&mut dxl),
+        Err(DynamixelError::Timeout)
+    ));
+    assert_eq!(dxl.statistics.get(1).checksum_errors, 1);
+}
+
+#[test]
+fn instruction_is_not_a_reply() {
+    let mut dxl = replay(PING.to_vec());
+
+    assert!(matches!(
+        ProtocolTwo::ping(&mut dxl),
+        Err(DynamixelError::Timeout)
+    ));
+}
+
+#[test]
+fn short_read_is_rejected() {
+    let sent = Packet::read(1, 0, 2).generate().unwrap();
+    let mut dxl = replay_exchange(sent, status(&[0x06]));
+
+    assert!(matches!(
+        dxl.detect_model(),
+        Err(DynamixelError::Packet(PacketReadError::InvalidLength))
+    ));
+}
+
+#[test]
+fn long_read_is_rejected() {
+    let sent = Packet::read(1, 0, 2).generate().unwrap();
+    let mut dxl = replay_exchange(sent, status(&[0x06, 0x04, 0x26]));
+
+    assert!(matches!(
+        ProtocolTwo::read(&mut dxl, 0, 2),
+        Err(DynamixelError::Packet(PacketReadError::InvalidLength))
+    ));
+}
+
+#[test]
+fn failed_writes_are_not_applied() {
+    let sent = Packet::write(1, 68, DataBytes::One(2)).generate().unwrap();
+    let mut dxl = replay_exchange(sent, vec![]);
+    dxl.set_model(builtin_model(1020).unwrap());
+    dxl.status_return_level = StatusReturnLevel::Read;
+
+    assert!(matches!(
+        ProtocolTwo::write_item(&mut dxl, "Status Return Level", 2),
+        Err(DynamixelError::Timeout)
+    ));
+    assert_eq!(dxl.status_return_level, StatusReturnLevel::Read);
+}
+
+#[test]
+fn return_delay_is_applied_after_the_write() {
+    let sent = Packet::write(1, 9, DataBytes::One(100)).generate().unwrap();
+    let mut dxl = replay_exchange(sent, status(&[]));
+    dxl.set_model(builtin_model(1020).unwrap());
+
+    ProtocolTwo::write_item(&mut dxl, "Return Delay Time", 100).unwrap();
+    assert_eq!(dxl.return_delay, Duration::from_micros(200));
+}
+
+#[test]
+fn operating_mode_write_forgets_the_mode() {
+    let sent = Packet::write(1, 11, DataBytes::One(1)).generate().unwrap();
+    let mut dxl = replay_exchange(sent, status(&[]));
+    dxl.set_model(builtin_model(1020).unwrap());
+    dxl.mode = Some(DynamixelMode::Joint);
+
+    ProtocolTwo::write_item(&mut dxl, "Operating Mode", 1).unwrap();
+    assert_eq!(dxl.mode, None);
+}
+
+#[test]
+fn components_are_written_with_protocol_two() {
+    let sent = Packet::write(1, 65, DataBytes::One(1)).generate().unwrap();
+    let mut dxl = replay_exchange(sent, status(&[]));
+    dxl.set_model(builtin_model(1020).unwrap());
+
+    dxl.led().on().unwrap();
+    assert_eq!(dxl.connection_handler.remaining(), 0);
+}
+
+#[test]
+fn protocol_one_operations_are_rejected() {
+    let mut dxl = replay(vec![]);
+    dxl.set_model(builtin_model(1020).unwrap());
+
+    let results = [
+        dxl.set_pos(2048),
+        dxl.set_speed(100),
+        dxl.set_mode(DynamixelMode::Wheel),
+        dxl.shutdown().get().map(|_| ()),
+    ];
+    for result in results.iter() {
+        assert!(matches!(
+            result,
+            Err(DynamixelError::UnsupportedProtocol {
+                protocol: Protocol::Two,
+                ..
+            })
+        ));
+    }
+
+    // Nothing was sent to the servo
+    assert_eq!(dxl.connection_handler.remaining(), 2);
+}
@@ -0,0 +1,42 @@
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::scan::{scan, ScanOptions};
+use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+use movement::dynamixel::Protocol;
+use std::time::Duration;
+
+#[test]
+fn slow_servo_is_found() {
+    // At 9600 baud a ping & its response take over 12ms on the wire, which
+    // is longer than a response is waited for at the default baud rate
+    let mut servo = SimulatedDynamixel::new(1, builtin_model(12).unwrap());
+    servo.set_item("Baud Rate", 207).unwrap();
+    let mut bus = SimulatedBus::new();
+    bus.add_servo(servo);
+    bus.delay_replies(Duration::from_millis(11));
+
+    let options = ScanOptions {
+        ids: 1..=1,
+        baud_rates: vec![9_600],
+        protocols: vec![Protocol::One],
+    };
+    let found = scan(&mut bus, &options, |_| {}).unwrap();
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].model_number, Some(12));
+}
+
+#[test]
+fn unanswered_ping_does_not_stop_the_scan() {
+    let mut bus = SimulatedBus::new();
+    bus.add_servo(SimulatedDynamixel::new(3, builtin_model(12).unwrap()));
+    bus.drop_replies(1);
+
+    let options = ScanOptions {
+        ids: 2..=4,
+        baud_rates: vec![1_000_000],
+        protocols: vec![Protocol::One],
+    };
+    let found = scan(&mut bus, &options, |_| {}).unwrap();
+
+    assert!(found.is_empty());
+}
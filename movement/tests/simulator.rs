@@ -0,0 +1,105 @@
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::protocol_one::ProtocolOne;
+use movement::dynamixel::simulator::{SimulatedBus, SimulatedDynamixel};
+use movement::dynamixel::{Dynamixel, DynamixelError, DynamixelID};
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+fn servo(id: u8) -> Dynamixel<SimulatedBus> {
+    let mut bus = SimulatedBus::new();
+    bus.add_servo(SimulatedDynamixel::new(id, builtin_model(12).unwrap()));
+    Dynamixel::new_empty(bus, DynamixelID::ID(id))
+}
+
+#[test]
+fn dropped_reply_times_out() {
+    let mut dxl = servo(1);
+    dxl.connection_handler.drop_replies(1);
+
+    assert!(matches!(dxl.ping(), Err(DynamixelError::Timeout)));
+    assert!(dxl.ping().is_ok());
+}
+
+#[test]
+fn dropped_reply_is_retried() {
+    let mut dxl = servo(1);
+    dxl.retry_policy.retries = 1;
+    dxl.connection_handler.drop_replies(1);
+
+    assert_eq!(dxl.read(43, 1).unwrap().parameters, vec![32]);
+    let statistics = dxl.statistics.get(1);
+    assert_eq!((statistics.sent, statistics.timeouts), (2, 1));
+}
+
+#[test]
+fn dropped_write_reply_still_writes() {
+    let mut dxl = servo(1);
+    dxl.connection_handler.drop_replies(1);
+
+    assert!(matches!(
+        dxl.write(25, 1u8.into()),
+        Err(DynamixelError::Timeout)
+    ));
+    assert_eq!(
+        dxl.connection_handler.servo(1).unwrap().get_item("LED"),
+        Some(1)
+    );
+}
+
+#[test]
+fn read_times_out_before_delayed_reply() {
+    let mut bus = SimulatedBus::new();
+    bus.add_servo(SimulatedDynamixel::new(1, builtin_model(12).unwrap()));
+    bus.set_timeout(Duration::from_millis(1));
+    bus.delay_replies(Duration::from_millis(20));
+
+    bus.write_all(&[0xFF, 0xFF, 0x01, 0x02, 0x01, 0xFB])
+        .unwrap();
+    let mut buf = [0u8; 16];
+    let err = bus.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::TimedOut);
+}
+
+#[test]
+fn delayed_reply_within_timeout_is_read() {
+    let mut dxl = servo(1);
+    dxl.connection_handler
+        .delay_replies(Duration::from_millis(2));
+
+    assert!(dxl.ping().is_ok());
+}
+
+#[test]
+fn delayed_reply_past_deadline_times_out() {
+    let mut dxl = servo(1);
+    dxl.retry_policy.timeout = Some(Duration::from_millis(2));
+    dxl.connection_handler.set_timeout(Duration::from_millis(1));
+    dxl.connection_handler
+        .delay_replies(Duration::from_millis(30));
+
+    assert!(matches!(dxl.ping(), Err(DynamixelError::Timeout)));
+}
+
+#[test]
+fn echo_is_discarded() {
+    let mut dxl = servo(1);
+    dxl.connection_handler.set_echo(true);
+
+    assert_eq!(dxl.read(43, 1).unwrap().parameters, vec![32]);
+    assert_eq!(dxl.ping().unwrap().id, 1);
+}
+
+#[test]
+fn late_reply_is_not_taken_for_the_next() {
+    let mut dxl = servo(1);
+    dxl.retry_policy.timeout = Some(Duration::from_millis(20));
+    dxl.connection_handler
+        .set_timeout(Duration::from_millis(10));
+    dxl.connection_handler
+        .delay_replies(Duration::from_millis(25));
+
+    assert!(matches!(dxl.read(3, 1), Err(DynamixelError::Timeout)));
+
+    dxl.connection_handler.delay_replies(Duration::from_secs(0));
+    assert_eq!(dxl.read(43, 1).unwrap().parameters, vec![32]);
+}
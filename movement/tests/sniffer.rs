@@ -0,0 +1,156 @@
+use movement::dynamixel::capture::Direction;
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::dissector::Dissector;
+use movement::dynamixel::protocol_one::{InstructionType, Packet, PacketType, StatusType};
+use movement::dynamixel::sniffer::{Sniffed, Sniffer};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+/// A connection which returns each chunk of bytes after a pause, then times
+/// out once every chunk has been read
+struct Script {
+    chunks: VecDeque<(Duration, Vec<u8>)>,
+}
+
+impl Read for Script {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.chunks.pop_front() {
+            Some((pause, bytes)) => {
+                thread::sleep(pause);
+                buf[..bytes.len()].copy_from_slice(&bytes);
+                Ok(bytes.len())
+            }
+            None => {
+                thread::sleep(Duration::from_millis(30));
+                Err(io::Error::new(io::ErrorKind::TimedOut, "silent"))
+            }
+        }
+    }
+}
+
+/// A protocol 1 instruction packet
+fn sent(id: u8, instruction: InstructionType, params: Vec<u8>) -> Vec<u8> {
+    Packet::new_raw(id, PacketType::Instruction(instruction), params).to_bytes()
+}
+
+/// A protocol 1 status packet
+fn reply(id: u8, errors: Vec<StatusType>, params: Vec<u8>) -> Vec<u8> {
+    Packet::new_raw(id, PacketType::Status(errors), params).to_bytes()
+}
+
+fn sniff(chunks: Vec<(u64, Vec<u8>)>) -> Vec<Sniffed> {
+    let chunks = chunks
+        .into_iter()
+        .map(|(pause, bytes)| (Duration::from_millis(pause), bytes))
+        .collect();
+    let mut sniffer = Sniffer::new(Script { chunks }, Dissector::new(builtin_model(12)));
+
+    let mut sniffed = vec![];
+    for _ in 0..4 {
+        sniffed.extend(sniffer.poll().unwrap());
+    }
+    sniffed.extend(sniffer.finish());
+
+    sniffed
+}
+
+fn response_delay(sniffed: &Sniffed) -> Duration {
+    match sniffed {
+        Sniffed::Exchange {
+            request,
+            response: Some(response),
+        } => response.time - request.time,
+        other => panic!("expected an answered exchange, found {:?}", other),
+    }
+}
+
+#[test]
+fn response_is_timed_when_read() {
+    // The noise looks like the start of a long packet, so the response
+    // behind it is only decoded once the bus falls silent
+    let mut response = vec![0xFF, 0xFF, 0x05, 0x40];
+    response.extend(reply(1, vec![], vec![]));
+    let sniffed = sniff(vec![
+        (0, sent(1, InstructionType::Ping, vec![])),
+        (10, response),
+    ]);
+
+    assert!(matches!(&sniffed[0], Sniffed::Unframed(_)));
+    let delay = response_delay(&sniffed[1]);
+    assert!(delay >= Duration::from_millis(10) && delay < Duration::from_millis(25));
+}
+
+#[test]
+fn read_after_unanswered_write_is_paired() {
+    let sniffed = sniff(vec![
+        (0, sent(1, InstructionType::Write, vec![25, 1])),
+        (2, sent(1, InstructionType::Read, vec![43, 1])),
+        (2, reply(1, vec![], vec![40])),
+    ]);
+
+    assert!(matches!(
+        &sniffed[0],
+        Sniffed::Exchange { response: None, .. }
+    ));
+    match &sniffed[1] {
+        Sniffed::Exchange {
+            request,
+            response: Some(response),
+        } => {
+            assert_eq!(request.frame.fields[0].value, "43 (Present Temperature)");
+            assert_eq!(
+                response.frame.fields[0].to_string(),
+                "Present Temperature (43) = 40"
+            );
+        }
+        other => panic!("expected an answered exchange, found {:?}", other),
+    }
+}
+
+#[test]
+fn capture_keeps_the_order_of_the_bus() {
+    let chunks = vec![
+        (
+            Duration::from_millis(0),
+            sent(1, InstructionType::Read, vec![43, 1]),
+        ),
+        (Duration::from_millis(2), reply(2, vec![], vec![40])),
+        (
+            Duration::from_millis(2),
+            sent(3, InstructionType::Ping, vec![]),
+        ),
+    ];
+    let mut sniffer = Sniffer::new(
+        Script {
+            chunks: chunks.into(),
+        },
+        Dissector::new(None),
+    );
+    sniffer.start_capture();
+
+    // The status from servo 2 is reported before the unanswered read
+    let mut sniffed = vec![];
+    for _ in 0..4 {
+        sniffed.extend(sniffer.poll().unwrap());
+    }
+    sniffed.extend(sniffer.finish());
+    assert!(matches!(&sniffed[0], Sniffed::Status(_)));
+
+    let captured = sniffer.take_captured();
+    let ids: Vec<(u8, Direction)> = captured
+        .iter()
+        .map(|packet| (packet.bytes[2], packet.direction))
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            (1, Direction::Sent),
+            (2, Direction::Received),
+            (3, Direction::Sent)
+        ]
+    );
+    assert!(captured[1].time - captured[0].time >= 2000);
+    assert!(sniffer.take_captured().is_empty());
+}
@@ -0,0 +1,28 @@
+use movement::dynamixel::control_table::builtin_model;
+use movement::dynamixel::telemetry::SensorCache;
+use movement::dynamixel::{Dynamixel, DynamixelID};
+
+#[test]
+fn reloading_the_model_replaces_its_sensors() {
+    let mut dxl = Dynamixel::new_empty(std::io::empty(), DynamixelID::ID(1));
+    dxl.set_model(builtin_model(12).unwrap());
+    dxl.sensor_cache.update(42, &[118, 32]);
+    dxl.sensor_cache.update(146, &[50]);
+
+    dxl.set_model(builtin_model(1020).unwrap());
+    assert!(!dxl.sensors.contains_key("Present Voltage"));
+    assert!(dxl.sensors.contains_key("Present Input Voltage"));
+
+    // Nothing read from the previous servo is reported by the new sensors
+    let temperature = dxl.sensors["Present Temperature"].get_data();
+    assert_eq!(temperature.value, 0);
+}
+
+#[test]
+fn data_past_the_last_address_is_ignored() {
+    let cache = SensorCache::new();
+    cache.update(0xFFFE, &[0x01, 0x02, 0x03, 0x04]);
+
+    assert_eq!(cache.get(0xFFFE, 2), Some(0x0201));
+    assert_eq!(cache.get(0xFFFF, 2), None);
+}
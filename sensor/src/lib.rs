@@ -1,8 +1,14 @@
 pub mod numeric_sensor;
+
+use serde::{Deserialize, Serialize};
+
 /// A representation of all common units of data that may be processed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DataUnit {
     Second,
     Pulse,
+    Degrees,
+    Radians,
     RevolutionsPerMinute,
     DegreesCelcius,
     Volts,
@@ -11,13 +17,42 @@ pub enum DataUnit {
     Other,
 }
 
-/// An abstract representation of data collected by the sensor
+/// An abstract representation of data collected by the sensor, where the
+/// value is scaled by 10 to the given power
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct DataValue<T> {
     pub unit: DataUnit,
     pub power: isize,
     pub value: T,
 }
 
+impl DataValue<isize> {
+    /// Creates a value from a floating point number, rounded to the nearest
+    /// multiple of 10 to the given power
+    ///
+    /// ```
+    /// use sensor::{DataUnit, DataValue};
+    ///
+    /// fn main() {
+    ///     let volts = DataValue::from_f64(DataUnit::Volts, -1, 11.96);
+    ///     assert_eq!(volts.value, 120);
+    ///     assert_eq!(volts.as_f64(), 12.0);
+    /// }
+    /// ```
+    pub fn from_f64(unit: DataUnit, power: isize, value: f64) -> Self {
+        DataValue {
+            unit,
+            power,
+            value: (value / 10f64.powi(power as i32)).round() as isize,
+        }
+    }
+
+    /// The value as a floating point number
+    pub fn as_f64(&self) -> f64 {
+        self.value as f64 * 10f64.powi(self.power as i32)
+    }
+}
+
 /// An abstract representation of a sensor on the robot
 pub struct Sensor<T> {
     pub model_name: String,